@@ -1,135 +1,192 @@
+mod report;
+mod tree;
+mod validate;
+
 use regex::Regex;
-use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::io;
 use std::io::Read;
 // use std::str::FromStr;
 
+use report::{Disk, Predicate};
+use tree::Tree;
+
+type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
 #[derive(Debug)]
 enum Ast {
     Cd(String),
     Ls,
     Dir(String),
-    File(String, u32),
+    File(String, u64),
+    Unknown(String),
 }
 
-fn parse(input: String) -> Vec<Ast> {
+// Every line as a command or `ls` output, along with its line number.
+fn parse(input: String) -> Vec<(usize, Ast)> {
     let cd_re   = Regex::new(r"^\$ cd (.*)$").unwrap();
     let ls_re   = Regex::new(r"^\$ ls$").unwrap();
     let dir_re  = Regex::new(r"^dir (.*)$").unwrap();
     let file_re = Regex::new(r"^(\d+) (.*)$").unwrap();
 
-    let mut entries: Vec<Ast> = vec![];
+    let mut entries: Vec<(usize, Ast)> = vec![];
+
+    for (n, line) in input.lines().enumerate() {
+        let line = line.trim_end();
+        let n = n + 1;
 
-    for line in input.lines() {
         if line.is_empty() {
             continue;
         }
 
         if let Some(captures) = cd_re.captures(line) {
-            entries.push(Ast::Cd(captures[1].to_string()));
+            entries.push((n, Ast::Cd(captures[1].to_string())));
             continue;
         }
 
         if let Some(_captures) = ls_re.captures(line) {
-            entries.push(Ast::Ls);
+            entries.push((n, Ast::Ls));
             continue;
         }
 
         if let Some(captures) = dir_re.captures(line) {
-            entries.push(Ast::Dir(captures[1].to_string()));
+            entries.push((n, Ast::Dir(captures[1].to_string())));
             continue;
         }
 
         if let Some(captures) = file_re.captures(line) {
-            let size: u32 = captures[1].parse().unwrap();
-            entries.push(Ast::File(captures[2].to_string(), size));
-            continue;
+            if let Ok(size) = captures[1].parse::<u64>() {
+                entries.push((n, Ast::File(captures[2].to_string(), size)));
+                continue;
+            }
         }
 
-        println!("line: {}", line);
-
-        unreachable!()
+        entries.push((n, Ast::Unknown(line.to_string())));
     }
 
     entries
 }
 
-#[derive(Debug)]
-enum FileType {
-    Dir(String, HashMap<String, FileType>, FileType),
-    File(String, u32, FileType),
+fn part1(tree: &Tree) {
+    let sum = tree.dirs()
+        .map(|id| tree.size(id))
+        .filter(|&size| size <= 100_000)
+        .sum::<u64>();
+
+    println!("Part 1: {}", sum);
 }
 
-impl FileType {
-    fn dir(name: &str) -> FileType {
-        FileType::Dir(name.to_string(), HashMap::new())
+fn part2(tree: &Tree, disk: &Disk) {
+    if disk.shortfall(tree) == 0 {
+        println!("Part 2: there's already enough free space, nothing needs deleting");
+        return
     }
 
-    fn file(name: &str, size: u32) -> FileType {
-        FileType::File(name.to_string(), size)
+    match disk.candidates(tree).first() {
+        Some(&id) => println!("Part 2: {}", tree.size(id)),
+        None => println!("Part 2: no single directory frees enough space"),
     }
+}
 
-    fn add_child(&mut self, path: &Vec<String>, child: FileType) {
-        match self {
-            FileType::Dir(name, children) => {
-                if path.len() == 1 && !children.contains_key(&path[0]) {
-                    children.insert(path[0], child);
-                }
+enum Mode {
+    Parts,
+    Tree,
+    Du,
+    Find,
+    Candidates,
+    Check,
+}
+
+fn main() -> Result<(), Error> {
+    let mut path = None;
+    let mut mode = Mode::Parts;
+    let mut predicates = vec![];
+    let mut disk = Disk { size: 70_000_000, needed: 30_000_000 };
+    let mut recover = false;
+
+    // `--tree` and `--du` print the filesystem, `--find PREDICATE` lists the
+    // directories whose size matches, like `--find '<=100000'`, and can be
+    // given more than once. `--candidates` lists the directories that could be
+    // deleted to make room, using `--disk-size` and `--needed` in place of the
+    // puzzle's numbers. `--check` only reports problems with the transcript,
+    // and `--recover` carries on past them with our best guess at what was
+    // meant instead of stopping. Anything else is the path to the input, or
+    // `-` for stdin.
+    let mut args = env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--tree") => mode = Mode::Tree,
+            Some("--du") => mode = Mode::Du,
+            Some("--candidates") => mode = Mode::Candidates,
+            Some("--check") => mode = Mode::Check,
+            Some("--recover") => recover = true,
+            Some("--find") => {
+                let value = args.next().ok_or("--find needs a predicate like <=100000")?;
+                predicates.push(Predicate::parse(value.to_str().ok_or("--find isn't valid UTF-8")?)?);
+                mode = Mode::Find;
             }
+            Some(option @ ("--disk-size" | "--needed")) => {
+                let value = args.next().ok_or_else(|| format!("{} needs a size", option))?;
+                let size = report::parse_size(value.to_str().ok_or_else(|| format!("{} isn't valid UTF-8", option))?)?;
 
-            FileType::File(_, _) => unreachable!(),
+                match option {
+                    "--disk-size" => disk.size = size,
+                    _ => disk.needed = size,
+                }
+            }
+            Some(option) if option.starts_with("--") => return Err(format!("unknown option {}", option).into()),
+            _ => path = Some(arg),
         }
     }
-}
 
-fn main() -> io::Result<()> {
-    let input = env::args().nth(1).unwrap();
+    let input = path.ok_or("no input file given, use - for stdin")?;
 
-    let mut rdr: Box<dyn io::Read> = match input.as_ref() {
-        "-" => Box::new(io::stdin()),
-        _   => Box::new(fs::File::open(input).expect("could not open file")),
+    let mut rdr: Box<dyn io::Read> = match input.to_str() {
+        Some("-") => Box::new(io::stdin()),
+        _   => Box::new(fs::File::open(&input)?),
     };
     let mut buf = String::new();
-    rdr.read_to_string(&mut buf).unwrap();
+    rdr.read_to_string(&mut buf)?;
 
     let entries = parse(buf);
+    let (tree, issues) = validate::replay(&entries);
 
-    let mut pointer = vec!["/".to_string()];
-    let mut tree = Tree::new();
-    tree.add_child(&pointer, FileType::dir("/"));
-
-    for entry in entries {
-        match entry {
-            Ast::Cd(dir) => {
-                match dir.as_ref() {
-                    "/"  => {
-                        pointer = vec!["/".to_string()];
-                    },
-                    ".." => {
-                        pointer.pop();
-                    },
-                    name => {
-                        pointer.push(name.to_string())
-                    },
-                }
-            },
-
-            Ast::Ls => {
-            },
+    if let Mode::Check = mode {
+        for issue in &issues {
+            println!("{}", issue);
+        }
 
-            Ast::Dir(name) => {
-                tree.add_child(&pointer, FileType::dir(&name))
+        return match issues.len() {
+            0 => {
+                println!("no problems found");
+                Ok(())
             }
+            n => Err(format!("found {} problems in the transcript", n).into()),
+        }
+    }
 
-            Ast::File(name, size) => {
-                tree.add_child(&pointer, FileType::file(&name, size));
-            }
+    if !issues.is_empty() {
+        for issue in &issues {
+            eprintln!("{}", issue);
+        }
+
+        if !recover {
+            return Err(format!("found {} problems in the transcript, use --recover to carry on anyway", issues.len()).into())
         }
     }
 
-    println!("{:?}", tree);
+    match mode {
+        Mode::Parts => {
+            part1(&tree);
+            part2(&tree, &disk);
+        }
+        Mode::Tree => report::print_tree(&tree),
+        Mode::Du => report::print_du(&tree),
+        Mode::Find => report::print_matching(&tree, &predicates),
+        Mode::Candidates => report::print_candidates(&tree, &disk),
+        Mode::Check => unreachable!(),
+    }
 
     Ok(())
 }
@@ -0,0 +1,159 @@
+use std::cmp::Reverse;
+
+use crate::tree::{FileType, Tree, ROOT};
+use crate::Error;
+
+// Prints the filesystem the way the puzzle shows it, with a directory's size
+// being everything under it.
+pub fn print_tree(tree: &Tree) {
+    fn visit(tree: &Tree, id: usize, depth: usize) {
+        let node = &tree.nodes[id];
+
+        match &node.kind {
+            FileType::Dir(children) => {
+                println!("{:indent$}- {} (dir, size={})", "", node.name, node.size, indent = depth * 2);
+                for &child in children.values() {
+                    visit(tree, child, depth + 1);
+                }
+            }
+            FileType::File(size) => {
+                println!("{:indent$}- {} (file, size={})", "", node.name, size, indent = depth * 2);
+            }
+        }
+    }
+
+    visit(tree, ROOT, 0);
+}
+
+// Every directory, biggest first.
+pub fn print_du(tree: &Tree) {
+    print_dirs(tree, tree.dirs().collect());
+}
+
+fn print_dirs(tree: &Tree, mut dirs: Vec<usize>) {
+    dirs.sort_by_key(|&id| (Reverse(tree.size(id)), tree.path(id)));
+
+    let width = dirs.iter().map(|&id| tree.size(id).to_string().len()).max().unwrap_or(0);
+    for id in dirs {
+        println!("{:>width$}  {}", tree.size(id), tree.path(id));
+    }
+}
+
+// A comparison against a directory's size, like `<=100000` or `>1_000_000`.
+#[derive(Clone, Copy, Debug)]
+pub struct Predicate {
+    op: Op,
+    size: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Predicate {
+    pub fn parse(text: &str) -> Result<Predicate, Error> {
+        let text = text.trim();
+
+        let (op, rest) = [
+            ("<=", Op::LessOrEqual),
+            (">=", Op::GreaterOrEqual),
+            ("<", Op::Less),
+            (">", Op::Greater),
+            ("=", Op::Equal),
+        ]
+        .into_iter()
+        .find_map(|(prefix, op)| Some((op, text.strip_prefix(prefix)?)))
+        .ok_or_else(|| format!("expected a comparison like <=100000, found {:?}", text))?;
+
+        Ok(Predicate { op, size: parse_size(rest)? })
+    }
+
+    pub fn matches(&self, size: u64) -> bool {
+        match self.op {
+            Op::Less => size < self.size,
+            Op::LessOrEqual => size <= self.size,
+            Op::Equal => size == self.size,
+            Op::GreaterOrEqual => size >= self.size,
+            Op::Greater => size > self.size,
+        }
+    }
+}
+
+// Sizes can be written with `_` or `,` between the digits.
+pub fn parse_size(text: &str) -> Result<u64, Error> {
+    let digits: String = text.trim().chars().filter(|&c| c != '_' && c != ',').collect();
+
+    digits.parse::<u64>()
+        .map_err(|err| format!("{:?} is not a size: {}", text, err).into())
+}
+
+// Directories that match every one of the predicates, biggest first.
+pub fn print_matching(tree: &Tree, predicates: &[Predicate]) {
+    let dirs: Vec<usize> = tree.dirs()
+        .filter(|&id| predicates.iter().all(|predicate| predicate.matches(tree.size(id))))
+        .collect();
+
+    println!("directories matching: {}", dirs.len());
+    print_dirs(tree, dirs);
+}
+
+// How much space the update needs freed and which directories could go.
+#[derive(Clone, Copy, Debug)]
+pub struct Disk {
+    pub size: u64,
+    pub needed: u64,
+}
+
+impl Disk {
+    pub fn shortfall(&self, tree: &Tree) -> u64 {
+        let free = self.size.saturating_sub(tree.size(ROOT));
+        self.needed.saturating_sub(free)
+    }
+
+    // Directories big enough that deleting any one of them frees enough
+    // space, smallest first.
+    pub fn candidates(&self, tree: &Tree) -> Vec<usize> {
+        let to_free = self.shortfall(tree);
+        let mut dirs: Vec<usize> = tree.dirs().filter(|&id| tree.size(id) >= to_free).collect();
+
+        dirs.sort_by_key(|&id| (tree.size(id), tree.path(id)));
+        dirs
+    }
+}
+
+pub fn print_candidates(tree: &Tree, disk: &Disk) {
+    let used = tree.size(ROOT);
+
+    println!("disk size: {}", disk.size);
+    println!("used:      {}", used);
+    println!("free:      {}", disk.size.saturating_sub(used));
+    println!("needed:    {}", disk.needed);
+
+    let to_free = disk.shortfall(tree);
+    if to_free == 0 {
+        println!("there's already enough free space");
+        return
+    }
+
+    println!("to free:   {}", to_free);
+
+    let candidates = disk.candidates(tree);
+    if candidates.is_empty() {
+        println!("deleting a single directory can't free enough space");
+        return
+    }
+
+    println!();
+    println!("directories that would free enough space: {}", candidates.len());
+
+    let width = candidates.iter().map(|&id| tree.size(id).to_string().len()).max().unwrap_or(0);
+    for (i, &id) in candidates.iter().enumerate() {
+        let note = if i == 0 { "  <- smallest" } else { "" };
+        println!("{:>width$}  {}{}", tree.size(id), tree.path(id), note);
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+
+pub const ROOT: usize = 0;
+
+#[derive(Debug)]
+pub enum FileType {
+    // Children by name.
+    Dir(BTreeMap<String, usize>),
+    File(u64),
+}
+
+#[derive(Debug)]
+pub struct Node {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub kind: FileType,
+    // For a directory, everything under it. Filled in by `compute_sizes`.
+    pub size: u64,
+}
+
+// Every file and directory we've seen, with `/` at index 0. Nodes refer to each
+// other by index, and a child always comes after its parent.
+#[derive(Debug)]
+pub struct Tree {
+    pub nodes: Vec<Node>,
+}
+
+impl Tree {
+    pub fn new() -> Tree {
+        Tree {
+            nodes: vec![Node {
+                name: "/".to_string(),
+                parent: None,
+                kind: FileType::Dir(BTreeMap::new()),
+                size: 0,
+            }],
+        }
+    }
+
+    pub fn child(&self, dir: usize, name: &str) -> Option<usize> {
+        match &self.nodes[dir].kind {
+            FileType::Dir(children) => children.get(name).copied(),
+            FileType::File(_) => None,
+        }
+    }
+
+    // Adds `name` to the directory and returns its index. If it's already there
+    // we keep what we have, so listing a directory twice doesn't count it twice.
+    pub fn add_child(&mut self, dir: usize, name: &str, kind: FileType) -> usize {
+        if let Some(existing) = self.child(dir, name) {
+            return existing
+        }
+
+        let size = match kind {
+            FileType::File(size) => size,
+            FileType::Dir(_) => 0,
+        };
+
+        let id = self.nodes.len();
+        self.nodes.push(Node { name: name.to_string(), parent: Some(dir), kind, size });
+
+        match &mut self.nodes[dir].kind {
+            FileType::Dir(children) => children.insert(name.to_string(), id),
+            FileType::File(_) => panic!("{} is a file", self.path(dir)),
+        };
+
+        id
+    }
+
+    pub fn is_dir(&self, id: usize) -> bool {
+        matches!(self.nodes[id].kind, FileType::Dir(_))
+    }
+
+    // Adds up the size of every directory. Children always come after their
+    // parents, so going backwards means each directory is finished before we
+    // add it to its parent.
+    pub fn compute_sizes(&mut self) {
+        for node in self.nodes.iter_mut() {
+            if let FileType::Dir(_) = node.kind {
+                node.size = 0;
+            }
+        }
+
+        for id in (1..self.nodes.len()).rev() {
+            let size = self.nodes[id].size;
+            let parent = self.nodes[id].parent.unwrap();
+            self.nodes[parent].size += size;
+        }
+    }
+
+    pub fn dirs(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.nodes.len()).filter(|&id| self.is_dir(id))
+    }
+
+    pub fn size(&self, id: usize) -> u64 {
+        self.nodes[id].size
+    }
+
+    pub fn path(&self, id: usize) -> String {
+        let mut names = vec![];
+        let mut current = Some(id);
+
+        while let Some(id) = current {
+            if id != ROOT {
+                names.push(self.nodes[id].name.as_str());
+            }
+            current = self.nodes[id].parent;
+        }
+
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use crate::tree::{FileType, Tree, ROOT};
+use crate::Ast;
+
+// Something in the transcript that doesn't add up, and what we did about it.
+#[derive(Debug)]
+pub struct Issue {
+    pub line: usize,
+    pub problem: Problem,
+}
+
+#[derive(Debug)]
+pub enum Problem {
+    // `cd ..` while already at `/`. We stay at `/`.
+    AboveRoot,
+    // `cd` into a directory that no `ls` had shown. We create it.
+    Unlisted { path: String },
+    // `cd` into something that was listed as a file. We stay where we are.
+    IntoFile { path: String },
+    // `ls` of a directory that was already listed. Anything it shows that we
+    // already have is kept as it was, so nothing is counted twice.
+    Relisted { path: String, first: usize },
+    // Output from `ls` with no `ls` before it. We add it to the current
+    // directory anyway.
+    Unprompted { text: String },
+    // A line that isn't a command or `ls` output. We skip it.
+    Unknown { text: String },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+
+        match &self.problem {
+            Problem::AboveRoot => write!(f, "cd .. at /"),
+            Problem::Unlisted { path } => write!(f, "cd into {}, which was never listed", path),
+            Problem::IntoFile { path } => write!(f, "cd into {}, which is a file", path),
+            Problem::Relisted { path, first } => write!(f, "{} was already listed on line {}", path, first),
+            Problem::Unprompted { text } => write!(f, "{:?} is ls output, but there was no ls", text),
+            Problem::Unknown { text } => write!(f, "{:?} is not a command or ls output", text),
+        }
+    }
+}
+
+// Replays the commands to rebuild the filesystem, starting at `/`. Anything
+// that doesn't make sense is reported, and the tree is our best guess at what
+// was meant.
+pub fn replay(entries: &[(usize, Ast)]) -> (Tree, Vec<Issue>) {
+    let mut tree = Tree::new();
+    let mut issues = vec![];
+    let mut cwd = ROOT;
+
+    // The line each directory was listed on, and everything a listing has
+    // shown.
+    let mut listed_on: HashMap<usize, usize> = HashMap::new();
+    let mut shown: HashSet<usize> = HashSet::from([ROOT]);
+    let mut listing = false;
+
+    for (line, entry) in entries {
+        let line = *line;
+        let mut report = |problem| issues.push(Issue { line, problem });
+
+        match entry {
+            Ast::Cd(dir) => {
+                listing = false;
+
+                cwd = match dir.as_ref() {
+                    "/" => ROOT,
+                    ".." => match tree.nodes[cwd].parent {
+                        Some(parent) => parent,
+                        None => {
+                            report(Problem::AboveRoot);
+                            ROOT
+                        }
+                    },
+                    name => match tree.child(cwd, name) {
+                        Some(id) if !tree.is_dir(id) => {
+                            report(Problem::IntoFile { path: tree.path(id) });
+                            cwd
+                        }
+                        Some(id) if shown.contains(&id) => id,
+                        _ => {
+                            let id = tree.add_child(cwd, name, FileType::Dir(BTreeMap::new()));
+                            report(Problem::Unlisted { path: tree.path(id) });
+                            id
+                        }
+                    },
+                }
+            },
+
+            Ast::Ls => {
+                listing = true;
+
+                match listed_on.get(&cwd) {
+                    Some(&first) => report(Problem::Relisted { path: tree.path(cwd), first }),
+                    None => {
+                        listed_on.insert(cwd, line);
+                    }
+                }
+            },
+
+            Ast::Dir(name) => {
+                if !listing {
+                    report(Problem::Unprompted { text: format!("dir {}", name) });
+                }
+
+                let id = tree.add_child(cwd, name, FileType::Dir(BTreeMap::new()));
+                shown.insert(id);
+            }
+
+            Ast::File(name, size) => {
+                if !listing {
+                    report(Problem::Unprompted { text: format!("{} {}", size, name) });
+                }
+
+                let id = tree.add_child(cwd, name, FileType::File(*size));
+                shown.insert(id);
+            }
+
+            Ast::Unknown(text) => report(Problem::Unknown { text: text.clone() }),
+        }
+    }
+
+    tree.compute_sizes();
+    (tree, issues)
+}
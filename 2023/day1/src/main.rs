@@ -1,132 +1,140 @@
-use std::env;
+mod scanner;
+
 use std::fs;
 use std::io;
 use std::io::Read;
-
-fn main() -> Result<(), std::io::Error> {
-    let input = env::args().nth(1).unwrap();
-
-    let mut rdr: Box<dyn io::Read> = match input.as_ref() {
-        "-" => Box::new(io::stdin()),
-        _   => Box::new(fs::File::open(input).expect("could not open file")),
-    };
-    let mut buf = String::new();
-    rdr.read_to_string(&mut buf).unwrap();
-    let input: Vec<&str> = buf.split('\n').filter(|val| !val.is_empty()).collect();
-
-    part1(input.clone());
-    part2(input);
-
-    Ok(())
-}
-
-fn part1(input: Vec<&str>) {
-    let mut nums = vec![];
-
-    for value in input {
-        if value.is_empty() {
-            continue;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use scanner::{Scanner, Vocabulary};
+
+type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+fn main() -> Result<(), Error> {
+    let mut path = None;
+    let mut files = vec![];
+    let mut explain = false;
+
+    // `--words FILE` swaps the English digit words for the ones in the file,
+    // and can be given more than once. `--explain` shows how part 2 read each
+    // line. Anything else is the path to the input, or `-` for stdin.
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--explain") => explain = true,
+            Some("--words") => {
+                let file = args.next().ok_or("--words needs a file")?;
+                files.push(PathBuf::from(file));
+            }
+            Some(option) if option.starts_with("--") => return Err(format!("unknown option {}", option).into()),
+            _ => path = Some(arg),
         }
+    }
 
-        let mut digits = vec![];
-
-        for char in value.chars() {
-            if char.is_ascii_digit() {
-                digits.push(char.to_digit(10).unwrap())
+    let vocabulary = match files.is_empty() {
+        true => Vocabulary::english(),
+        false => {
+            let mut vocabulary = Vocabulary::default();
+            for file in &files {
+                vocabulary.load(file)?;
             }
+            vocabulary
         }
+    };
 
-        if !digits.is_empty() {
-            let num = digits.first().unwrap() * 10 + digits.last().unwrap();
-            nums.push(num);
-        }
-    }
+    let input = path.ok_or("no input file given, use - for stdin")?;
 
-    let sum = nums.iter().sum::<u32>();
+    let mut rdr: Box<dyn io::Read> = match input.to_str() {
+        Some("-") => Box::new(io::stdin()),
+        _ => Box::new(fs::File::open(&input)?),
+    };
+    let mut buf = String::new();
+    rdr.read_to_string(&mut buf)?;
+    let scanner = Scanner::new(&vocabulary);
 
-    println!("Part 1: {}", sum);
-}
+    if explain {
+        explain_lines(&buf, &scanner);
+    }
 
-fn part2(input: Vec<&str>) {
-    let mut nums = vec![];
-
-    for value in input {
-        let mut digits = vec![];
-        let mut i = 0;
-        let chars: Vec<char> = value.chars().collect();
-        let replacements = vec![
-            ("one", 1),
-            ("two", 2),
-            ("three", 3),
-            ("four", 4),
-            ("five", 5),
-            ("six", 6),
-            ("seven", 7),
-            ("eight", 8),
-            ("nine", 9),
-        ];
-
-        loop {
-            if i >= chars.len() {
-                break;
-            }
+    let input: Vec<&str> = buf.lines().filter(|val| !val.is_empty()).collect();
 
-            if chars[i].is_ascii_digit() {
-                digits.push(chars[i].to_digit(10).unwrap());
-                i += 1;
-                continue;
-            }
+    part1(&input);
+    part2(&input, &scanner);
 
-            if let Some((digit, inc)) = match_words(&replacements, &chars[i..]) {
-                i += inc;
-                digits.push(digit);
-                continue;
-            }
+    Ok(())
+}
 
-            i += 1;
-        }
+fn part1(input: &[&str]) {
+    let scanner = Scanner::digits();
+    let sum = input.iter()
+        .filter_map(|line| scanner.value(line))
+        .sum::<u32>();
 
-        if !digits.is_empty() {
-            let num = digits.first().unwrap() * 10 + digits.last().unwrap();
-            nums.push(num);
-            println!("{} -> {}", value, num);
-        }
-    }
+    println!("Part 1: {}", sum);
+}
 
-    let sum = nums.iter().sum::<u32>();
+fn part2(input: &[&str], scanner: &Scanner) {
+    let sum = input.iter()
+        .filter_map(|line| scanner.value(line))
+        .sum::<u32>();
 
     println!("Part 2: {}", sum);
 }
 
-fn match_words(words: &Vec<(&str, u32)>, chars: &[char]) -> Option<(u32, usize)> {
-    for (word, digit) in words {
-        if is_match(word, chars) {
-            return Some((*digit, word.len()-1));
+// Prints every line with what the scanner found in it underneath. The first
+// and last matches, the ones that make up the value, are marked with `^` and
+// anything else with `-`.
+fn explain_lines(input: &str, scanner: &Scanner) {
+    for (n, line) in input.lines().enumerate() {
+        if line.is_empty() {
+            continue
         }
-    }
 
-    None
-}
+        let prefix = format!("line {}: ", n + 1);
+        println!("{}{}", prefix, line);
 
-fn is_match(word: &str, chars: &[char]) -> bool {
-    let word_chars: Vec<char> = word.chars().collect();
-    let mut i = 0;
+        let (first, last) = match (scanner.first(line), scanner.last(line)) {
+            (Some(first), Some(last)) => (first, last),
+            _ => {
+                println!("{:>width$}no digits, this line adds nothing", "", width = prefix.len());
+                println!();
+                continue
+            }
+        };
 
-    while i < word_chars.len() {
-        // If we're about to exceed the amount of chars we have don't bother
-        // continuing
-        if i >= chars.len() {
-            return false;
-        }
+        let found = scanner.all(line);
+
+        // Mark under each character rather than each byte, so words with
+        // accents still line up.
+        let mut marks: Vec<char> = vec![' '; line.chars().count()];
+        let mut mark = |span: &Range<usize>, with: char| {
+            let start = line[..span.start].chars().count();
+            let end = line[..span.end].chars().count();
+            marks[start..end].fill(with);
+        };
 
-        // If the characters don't match, return a false immediately.
-        if word_chars[i] != chars[i] {
-            return false
+        for f in &found {
+            mark(&f.span, '-');
+        }
+        mark(&first.span, '^');
+        mark(&last.span, '^');
+
+        println!("{:>width$}{}", "", marks.into_iter().collect::<String>().trim_end(), width = prefix.len());
+
+        for f in &found {
+            let role = match (f == &first, f == &last) {
+                (true, true) => "first and last",
+                (true, false) => "first",
+                (false, true) => "last",
+                (false, false) => "",
+            };
+
+            let text = format!("{:?}", &line[f.span.clone()]);
+            let details = format!("{:<10} {:>2} at {}..{} {}", text, f.value, f.span.start, f.span.end, role);
+            println!("  {}", details.trim_end());
         }
 
-        i += 1;
+        println!("  value: {}", first.value * 10 + last.value);
+        println!();
     }
-
-    // If we got here it means that all of the characters matched.
-    true
 }
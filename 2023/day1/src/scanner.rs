@@ -0,0 +1,163 @@
+use std::ops::Range;
+use std::path::Path;
+
+use aho_corasick::{AhoCorasick, MatchKind};
+
+type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+const ENGLISH: [(&str, u32); 9] = [
+    ("one", 1),
+    ("two", 2),
+    ("three", 3),
+    ("four", 4),
+    ("five", 5),
+    ("six", 6),
+    ("seven", 7),
+    ("eight", 8),
+    ("nine", 9),
+];
+
+// The words we recognise as digits, on top of the digits themselves.
+#[derive(Clone, Debug, Default)]
+pub struct Vocabulary {
+    pub words: Vec<(String, u32)>,
+}
+
+impl Vocabulary {
+    pub fn english() -> Vocabulary {
+        Vocabulary {
+            words: ENGLISH.iter().map(|&(word, value)| (word.to_string(), value)).collect(),
+        }
+    }
+
+    // Adds the words from a file with one `word = value` per line. Blank lines
+    // and anything after a `#` are ignored.
+    pub fn load(&mut self, path: &Path) -> Result<(), Error> {
+        let contents = std::fs::read_to_string(path)?;
+
+        for (n, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+
+            if line.is_empty() {
+                continue
+            }
+
+            let (word, value) = line.split_once('=')
+                .ok_or_else(|| format!("{}:{}: expected `word = value`", path.display(), n + 1))?;
+
+            let word = word.trim();
+            if word.is_empty() {
+                return Err(format!("{}:{}: the word is empty", path.display(), n + 1).into())
+            }
+
+            let value = value.trim().parse::<u32>()
+                .map_err(|err| format!("{}:{}: {:?} is not a number: {}", path.display(), n + 1, value.trim(), err))?;
+
+            self.words.push((word.to_string(), value));
+        }
+
+        Ok(())
+    }
+}
+
+// A digit or word found in a line, and where it was.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Found {
+    pub value: u32,
+    pub span: Range<usize>,
+}
+
+// Finds digits and digit words in a line. Words can overlap, like the "eight"
+// and "two" in "eightwo", so rather than walking the line and skipping over
+// what we've matched, the first digit comes from searching forwards and the
+// last one from searching the reversed line with the reversed words.
+pub struct Scanner {
+    values: Vec<u32>,
+    forward: AhoCorasick,
+    backward: AhoCorasick,
+    // Reports every match, overlapping or not, for explaining a line.
+    overlapping: AhoCorasick,
+}
+
+impl Scanner {
+    // Only the digits 0 to 9.
+    pub fn digits() -> Scanner {
+        Scanner::new(&Vocabulary::default())
+    }
+
+    pub fn new(vocabulary: &Vocabulary) -> Scanner {
+        let mut patterns: Vec<String> = (0..10).map(|digit| digit.to_string()).collect();
+        let mut values: Vec<u32> = (0..10).collect();
+
+        // A word we've already got replaces the earlier one, so a vocabulary
+        // file can redefine a digit.
+        for (word, value) in &vocabulary.words {
+            match patterns.iter().position(|pattern| pattern == word) {
+                Some(i) => values[i] = *value,
+                None => {
+                    patterns.push(word.clone());
+                    values.push(*value);
+                }
+            }
+        }
+
+        // When one word is the start of another, like "seven" and "seventeen",
+        // the longer one wins.
+        let forward = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(&patterns)
+            .expect("patterns are valid");
+
+        let backward = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(patterns.iter().map(|pattern| reversed(pattern.as_bytes())))
+            .expect("patterns are valid");
+
+        let overlapping = AhoCorasick::new(&patterns).expect("patterns are valid");
+
+        Scanner { values, forward, backward, overlapping }
+    }
+
+    pub fn first(&self, line: &str) -> Option<Found> {
+        self.forward.find(line).map(|found| Found {
+            value: self.values[found.pattern().as_usize()],
+            span: found.range(),
+        })
+    }
+
+    // The match that ends last. The search stops at the first match in the
+    // reversed line, so it only looks at the end of the line.
+    pub fn last(&self, line: &str) -> Option<Found> {
+        let bytes = reversed(line.as_bytes());
+
+        self.backward.find(&bytes).map(|found| Found {
+            value: self.values[found.pattern().as_usize()],
+            span: line.len() - found.end()..line.len() - found.start(),
+        })
+    }
+
+    // Every digit and word in the line, in the order they start.
+    pub fn all(&self, line: &str) -> Vec<Found> {
+        let mut all: Vec<Found> = self.overlapping.find_overlapping_iter(line)
+            .map(|found| Found {
+                value: self.values[found.pattern().as_usize()],
+                span: found.range(),
+            })
+            .collect();
+
+        all.sort_by_key(|found| (found.span.start, found.span.end));
+        all
+    }
+
+    // The calibration value for a line, if it has any digits in it.
+    pub fn value(&self, line: &str) -> Option<u32> {
+        let first = self.first(line)?;
+        let last = self.last(line)?;
+
+        Some(first.value * 10 + last.value)
+    }
+}
+
+fn reversed(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().rev().copied().collect()
+}
@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::{Error, Handful};
+
+// How many cubes of each colour are in the bag. A colour that isn't listed
+// has none.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bag {
+    pub cubes: BTreeMap<String, u32>,
+}
+
+impl Bag {
+    // The bag from the puzzle.
+    pub fn standard() -> Bag {
+        let mut bag = Bag::default();
+        bag.cubes.insert("red".to_string(), 12);
+        bag.cubes.insert("green".to_string(), 13);
+        bag.cubes.insert("blue".to_string(), 14);
+        bag
+    }
+
+    pub fn set(&mut self, color: &str, count: &str) -> Result<(), Error> {
+        if color.is_empty() || !color.chars().all(|c| c.is_alphabetic()) {
+            return Err(format!("{:?} is not a colour", color).into())
+        }
+
+        let count = count.parse::<u32>()
+            .map_err(|err| format!("{:?} is not a number of cubes: {}", count, err))?;
+
+        self.cubes.insert(color.to_string(), count);
+        Ok(())
+    }
+
+    // Sets colours from a list like `red=12,green=13,blue=14`.
+    pub fn set_all(&mut self, spec: &str) -> Result<(), Error> {
+        for part in spec.split(',') {
+            let (color, count) = part.split_once('=')
+                .ok_or_else(|| format!("expected `colour=count`, found {:?}", part))?;
+
+            self.set(color.trim(), count.trim())?;
+        }
+
+        Ok(())
+    }
+
+    // Sets colours from a file with one `colour = count` per line. Blank lines
+    // and anything after a `#` are ignored.
+    pub fn load(&mut self, path: &Path) -> Result<(), Error> {
+        let config = std::fs::read_to_string(path)?;
+
+        for (n, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+
+            if line.is_empty() {
+                continue
+            }
+
+            let (color, count) = line.split_once('=')
+                .ok_or_else(|| format!("{}:{}: expected `colour = count`", path.display(), n + 1))?;
+
+            self.set(color.trim(), count.trim())
+                .map_err(|err| format!("{}:{}: {}", path.display(), n + 1, err))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn count(&self, color: &str) -> u32 {
+        self.cubes.get(color).copied().unwrap_or(0)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.cubes.values().map(|&n| u64::from(n)).sum()
+    }
+
+    pub fn colors(&self) -> Vec<String> {
+        self.cubes.keys().cloned().collect()
+    }
+
+    // Like `12 red, 13 green`, with every colour in `colors` listed even if
+    // the bag has none of it.
+    pub fn display(&self, colors: &[String]) -> String {
+        let parts: Vec<String> = colors.iter().map(|color| format!("{} {}", self.count(color), color)).collect();
+        parts.join(", ")
+    }
+
+    pub fn allows(&self, handful: &Handful) -> bool {
+        handful.iter().all(|(color, &n)| n <= self.count(color))
+    }
+}
@@ -1,32 +1,112 @@
-use std::env;
+mod bag;
+mod query;
+
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 use std::io;
 use std::io::Read;
+use std::path::PathBuf;
 
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::{digit1, line_ending, space0},
-    combinator::map_res,
+    character::complete::{alpha1, digit1, line_ending, space0},
+    combinator::{eof, map_res},
     error::VerboseError,
     multi::many0,
     sequence::tuple,
     IResult,
 };
 
-fn main() -> Result<(), std::io::Error> {
-    let input = env::args().nth(1).unwrap();
+use bag::Bag;
+use query::{colors, minimum_bag, possible, smallest_bag};
+
+type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+enum Mode {
+    Parts,
+    // The fewest cubes of each colour every game needs.
+    Minimum,
+    // Which games the bag allows.
+    Possible,
+    // The bag with the fewest cubes that allows at least this many games.
+    Smallest(usize),
+}
 
-    let mut rdr: Box<dyn io::Read> = match input.as_ref() {
-        "-" => Box::new(io::stdin()),
-        _ => Box::new(fs::File::open(input).expect("could not open file")),
+fn main() -> Result<(), Error> {
+    let mut path = None;
+    let mut bag = Bag::standard();
+    let mut mode = Mode::Parts;
+
+    // `--config FILE` and `--bag red=12,green=13` change what's in the bag for
+    // part 1 and `--possible`, on top of the puzzle's 12 red, 13 green and 14
+    // blue, and can be given more than once. `--minimum`, `--possible` and
+    // `--smallest N` answer those questions instead of solving the puzzle.
+    // Anything else is the path to the input, or `-` for stdin.
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--minimum") => mode = Mode::Minimum,
+            Some("--possible") => mode = Mode::Possible,
+            Some("--smallest") => {
+                let n = args.next()
+                    .and_then(|value| value.to_str()?.parse().ok())
+                    .ok_or("--smallest needs a number of games")?;
+                mode = Mode::Smallest(n);
+            }
+            Some("--config") => {
+                let file = args.next().ok_or("--config needs a file")?;
+                bag.load(&PathBuf::from(file))?;
+            }
+            Some("--bag") => {
+                let spec = args.next().ok_or("--bag needs a list like red=12,green=13")?;
+                bag.set_all(spec.to_str().ok_or("--bag isn't valid UTF-8")?)?;
+            }
+            Some(option) if option.starts_with("--") => return Err(format!("unknown option {}", option).into()),
+            _ => path = Some(arg),
+        }
+    }
+
+    let input = path.ok_or("no input file given, use - for stdin")?;
+
+    let mut rdr: Box<dyn io::Read> = match input.to_str() {
+        Some("-") => Box::new(io::stdin()),
+        _ => Box::new(fs::File::open(&input)?),
     };
     let mut buf = String::new();
-    rdr.read_to_string(&mut buf).unwrap();
-    let input = parse(&buf);
+    rdr.read_to_string(&mut buf)?;
+    let input = parse(&buf)?;
 
-    part1(input.clone());
-    part2(input);
+    match mode {
+        Mode::Parts => {
+            part1(&input, &bag);
+            part2(&input)?;
+        }
+        Mode::Minimum => {
+            let colors = colors(&input);
+            for game in &input {
+                println!("Game {}: {}", game.id, minimum_bag(game).display(&colors));
+            }
+        }
+        Mode::Possible => {
+            let ids: Vec<String> = possible(&input, &bag).map(|game| game.id.to_string()).collect();
+            println!("{} of {} games are possible with {}", ids.len(), input.len(), bag.display(&bag.colors()));
+            if !ids.is_empty() {
+                println!("Games: {}", ids.join(", "));
+            }
+        }
+        Mode::Smallest(n) => match smallest_bag(&input, n) {
+            Some(smallest) => {
+                let ids: Vec<String> = possible(&input, &smallest).map(|game| game.id.to_string()).collect();
+                println!("{} cubes: {}", smallest.total(), smallest.display(&colors(&input)));
+                if !ids.is_empty() {
+                    println!("Games: {}", ids.join(", "));
+                }
+            }
+            None => return Err(format!("there are only {} games", input.len()).into()),
+        },
+    }
 
     Ok(())
 }
@@ -37,19 +117,8 @@ struct Game {
     handfuls: Vec<Handful>,
 }
 
-#[derive(Clone, Debug)]
-enum Color {
-    Red(u32),
-    Blue(u32),
-    Green(u32),
-}
-
-#[derive(Clone, Debug)]
-struct Handful {
-    red: u32,
-    blue: u32,
-    green: u32,
-}
+// How many cubes of each colour were shown.
+type Handful = BTreeMap<String, u32>;
 
 type Res<T, U> = IResult<T, U, VerboseError<T>>;
 
@@ -57,54 +126,32 @@ fn num(input: &str) -> Res<&str, u32> {
     map_res(digit1, str::parse)(input)
 }
 
-fn pcolor(input: &str) -> Res<&str, Color> {
-    let color = alt((tag("red"), tag("green"), tag("blue")));
-
-    tuple((num, space0, color))(input).map(|(next, res)| {
-        let c = match res.2 {
-            "red" => Color::Red(res.0),
-            "blue" => Color::Blue(res.0),
-            "green" => Color::Green(res.0),
-            _ => unreachable!()
-        };
-
-        (next, c)
-    })
+fn pcolor(input: &str) -> Res<&str, (&str, u32)> {
+    tuple((num, space0, alpha1))(input).map(|(next, res)| (next, (res.2, res.0)))
 }
 
 fn game(input: &str) -> Res<&str, u32> {
     tuple((tag("Game"), space0, num, tag(":"), space0))(input).map(|(next, res)| (next, res.2))
 }
 
-fn handful(input: &str) -> Res<&str, Handful> {
+// A handful as written, before we've checked it doesn't list a colour twice.
+type Shown<'a> = Vec<(&'a str, u32)>;
+
+fn handful(input: &str) -> Res<&str, Shown<'_>> {
     tuple((pcolor, many0(tuple((tag(","), space0, pcolor)))))(input).map(|(next, res)| {
-        let mut colors: Vec<Color> = vec![];
+        let mut colors = vec![];
         colors.push(res.0);
 
         for color in res.1 {
             colors.push(color.2)
         }
 
-        let mut h = Handful{
-            red: 0,
-            blue: 0,
-            green: 0
-        };
-
-        for color in colors {
-            match color {
-                Color::Red(n) => h.red = n,
-                Color::Blue(n) => h.blue = n,
-                Color::Green(n) => h.green = n,
-            }
-        }
-
-        (next, h)
+        (next, colors)
     })
 }
 
 
-fn handfuls(input: &str) -> Res<&str, Vec<Handful>> {
+fn handfuls(input: &str) -> Res<&str, Vec<Shown<'_>>> {
     tuple((handful, many0(tuple((tag(";"), space0, handful)))))(input).map(|(next, res)| {
         let mut handfuls = vec![];
         handfuls.push(res.0);
@@ -116,43 +163,103 @@ fn handfuls(input: &str) -> Res<&str, Vec<Handful>> {
     })
 }
 
-fn games(input: &str) -> Res<&str, Game> {
+fn games(input: &str) -> Res<&str, (u32, Vec<Shown<'_>>)> {
     tuple((
         game,
         handfuls,
-        line_ending,
+        alt((line_ending, eof)),
     ))(input)
-    .map(|(next, res)| {
-        let game = Game{
-            id: res.0,
-            handfuls: res.1
-        };
+    .map(|(next, res)| (next, (res.0, res.1)))
+}
 
-        (next, game)
-    })
+// A handful that lists the same colour more than once, like `3 red, 4 red`.
+#[derive(Debug)]
+struct Duplicate {
+    game: u32,
+    handful: usize,
+    color: String,
+    counts: Vec<u32>,
+}
+
+impl fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts: Vec<String> = self.counts.iter().map(|n| n.to_string()).collect();
+        write!(f, "game {}, handful {}: {} is listed {} times ({})", self.game, self.handful, self.color, self.counts.len(), counts.join(", "))
+    }
 }
 
-fn parse(input: &str) -> Vec<Game> {
-    many0(games)(input).unwrap().1
+fn parse(input: &str) -> Result<Vec<Game>, Error> {
+    let (rest, raw) = many0(games)(input).map_err(|err| format!("{:?}", err))?;
+
+    if !rest.trim().is_empty() {
+        let line = input[..input.len() - rest.len()].lines().count() + 1;
+        let text = rest.lines().next().unwrap_or_default();
+        return Err(format!("line {}: expected a game like `Game 1: 3 blue, 4 red; 2 green`, found {:?}", line, text).into())
+    }
+
+    let mut games = vec![];
+    let mut duplicates = vec![];
+
+    for (id, shown) in raw {
+        let mut handfuls = vec![];
+
+        for (i, colors) in shown.into_iter().enumerate() {
+            let mut h = Handful::new();
+
+            for &(color, n) in &colors {
+                h.insert(color.to_string(), n);
+            }
+
+            for color in h.keys() {
+                let counts: Vec<u32> = colors.iter().filter(|(c, _)| c == color).map(|&(_, n)| n).collect();
+
+                if counts.len() > 1 {
+                    duplicates.push(Duplicate { game: id, handful: i + 1, color: color.clone(), counts });
+                }
+            }
+
+            handfuls.push(h);
+        }
+
+        games.push(Game { id, handfuls });
+    }
+
+    if !duplicates.is_empty() {
+        for duplicate in &duplicates {
+            eprintln!("{}", duplicate);
+        }
+        return Err(format!("{} handfuls list a colour more than once", duplicates.len()).into())
+    }
+
+    Ok(games)
 }
 
-fn part1(games: Vec<Game>) {
-    let sum = games.iter().filter(|game| game.handfuls.iter().all(|hf| hf.red <= 12 && hf.green <= 13 && hf.blue <= 14))
-        .map(|game| game.id)
-        .sum::<u32>();
+fn part1(games: &[Game], bag: &Bag) {
+    let sum = possible(games, bag)
+        .map(|game| u64::from(game.id))
+        .sum::<u64>();
 
     println!("Part 1: {}", sum);
 }
 
-fn part2(games: Vec<Game>) {
-    let sum = games.iter()
-        .map(|game| {
-            (game.handfuls.iter().map(|hf| hf.red).max().unwrap(),
-             game.handfuls.iter().map(|hf| hf.green).max().unwrap(),
-             game.handfuls.iter().map(|hf| hf.blue).max().unwrap())
-        })
-        .map(|(r,g,b)| r*g*b)
-        .sum::<u32>();
+// The power of a game is the product of the fewest cubes of each colour it
+// needs. That's over every colour in the input, so a game that never shows
+// one of them has a power of 0, same as with the puzzle's three colours.
+// With enough colours this gets big quickly, so it's done in u64 and is an
+// error rather than a wrong answer if even that isn't enough.
+fn part2(games: &[Game]) -> Result<(), Error> {
+    let colors = colors(games);
+    let mut sum: u64 = 0;
+
+    for game in games {
+        let bag = minimum_bag(game);
+        let power = colors.iter()
+            .try_fold(1u64, |power, color| power.checked_mul(u64::from(bag.count(color))))
+            .ok_or_else(|| format!("the power of game {} is too big", game.id))?;
+
+        sum = sum.checked_add(power).ok_or("the sum of the powers is too big")?;
+    }
 
     println!("Part 2: {}", sum);
+    Ok(())
 }
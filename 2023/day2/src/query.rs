@@ -0,0 +1,127 @@
+use std::collections::BTreeSet;
+
+use crate::{bag::Bag, Game};
+
+// Every colour that shows up in any game.
+pub fn colors(games: &[Game]) -> Vec<String> {
+    let colors: BTreeSet<&String> = games.iter()
+        .flat_map(|game| game.handfuls.iter())
+        .flat_map(|hf| hf.keys())
+        .collect();
+
+    colors.into_iter().cloned().collect()
+}
+
+// The fewest cubes of each colour that could have been in the bag.
+pub fn minimum_bag(game: &Game) -> Bag {
+    let mut bag = Bag::default();
+
+    for hf in &game.handfuls {
+        for (color, &n) in hf {
+            let count = bag.cubes.entry(color.clone()).or_insert(0);
+            *count = (*count).max(n);
+        }
+    }
+
+    bag
+}
+
+pub fn is_possible(game: &Game, bag: &Bag) -> bool {
+    game.handfuls.iter().all(|hf| bag.allows(hf))
+}
+
+pub fn possible<'a>(games: &'a [Game], bag: &'a Bag) -> impl Iterator<Item = &'a Game> + 'a {
+    games.iter().filter(move |game| is_possible(game, bag))
+}
+
+// The bag with the fewest cubes in total that makes at least `n` games
+// possible, or None if there aren't that many games.
+//
+// The best bag always has exactly as many of each colour as some game needs,
+// so we try those counts for every colour but the last, keeping the games that
+// still fit. The last colour is then whatever the n-th most frugal of those
+// games needs. That's exponential in the number of colours, but there are only
+// ever a handful of colours.
+pub fn smallest_bag(games: &[Game], n: usize) -> Option<Bag> {
+    if n > games.len() {
+        return None
+    }
+
+    if n == 0 {
+        return Some(Bag::default())
+    }
+
+    let colors = colors(games);
+    let needs: Vec<Vec<u32>> = games.iter()
+        .map(|game| {
+            let bag = minimum_bag(game);
+            colors.iter().map(|color| bag.count(color)).collect()
+        })
+        .collect();
+
+    let mut search = Search { needs: &needs, n, counts: vec![], best: None };
+    search.visit(&(0..games.len()).collect::<Vec<usize>>(), 0);
+
+    let (_, counts) = search.best?;
+    let mut bag = Bag::default();
+    for (color, count) in colors.into_iter().zip(counts) {
+        bag.cubes.insert(color, count);
+    }
+
+    Some(bag)
+}
+
+struct Search<'a> {
+    // needs[game][color]
+    needs: &'a [Vec<u32>],
+    n: usize,
+    // The counts picked so far, one per colour.
+    counts: Vec<u32>,
+    best: Option<(u64, Vec<u32>)>,
+}
+
+impl Search<'_> {
+    fn visit(&mut self, fitting: &[usize], total: u64) {
+        let color = self.counts.len();
+        let colors = self.needs.first().map_or(0, |needs| needs.len());
+
+        if color == colors {
+            if self.best.as_ref().is_none_or(|(best, _)| total < *best) {
+                self.best = Some((total, self.counts.clone()));
+            }
+            return
+        }
+
+        let mut candidates: Vec<u32> = fitting.iter().map(|&game| self.needs[game][color]).collect();
+        candidates.sort_unstable();
+
+        // For the last colour the answer is just the n-th smallest need.
+        if color + 1 == colors {
+            candidates.truncate(self.n);
+            candidates.drain(..self.n.saturating_sub(1));
+        } else {
+            candidates.dedup();
+        }
+
+        for count in candidates {
+            // Counts only go up from here, so once we're past the best there's
+            // nothing left to find.
+            if self.best.as_ref().is_some_and(|(best, _)| total + u64::from(count) >= *best) {
+                break
+            }
+
+            let still_fitting: Vec<usize> = fitting.iter()
+                .copied()
+                .filter(|&game| self.needs[game][color] <= count)
+                .collect();
+
+            if still_fitting.len() < self.n {
+                continue
+            }
+
+            self.counts.push(count);
+            self.visit(&still_fitting, total + u64::from(count));
+            self.counts.pop();
+        }
+    }
+}
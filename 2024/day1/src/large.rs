@@ -0,0 +1,125 @@
+use std::io::BufRead;
+
+use crate::{Columns, Skipped};
+
+// Reads the columns straight out of the raw bytes, a buffer at a time, so we
+// never hold more than the lists themselves in memory. This is a lot faster
+// than matching every line with a regex when there are tens of millions of
+// rows.
+//
+// Like the regex parser, a line only counts if it is nothing but numbers
+// separated by whitespace, and as many of them as the first row. Anything else
+// that isn't blank is skipped and handed back to the caller.
+pub fn read_columns<R: BufRead>(mut reader: R) -> std::io::Result<(Columns, Skipped)> {
+    let mut columns = Columns::default();
+    let mut skipped = Skipped::default();
+    let mut line = Line::default();
+    let mut number = 1;
+
+    loop {
+        let buf = reader.fill_buf()?;
+
+        if buf.is_empty() {
+            break
+        }
+
+        for &byte in buf {
+            if byte == b'\n' {
+                line.finish(number, &mut columns, &mut skipped);
+                number += 1;
+                continue
+            }
+
+            line.text.push(byte);
+
+            match byte {
+                b'0'..=b'9' => line.digit(byte),
+                b'-' => line.minus(),
+                b' ' | b'\t' | b'\r' => line.space(),
+                _ => line.valid = false,
+            }
+        }
+
+        let len = buf.len();
+        reader.consume(len);
+    }
+
+    // The last line might not end in a newline.
+    line.finish(number, &mut columns, &mut skipped);
+
+    Ok((columns, skipped))
+}
+
+// The line we're in the middle of reading. `text` is only needed if the line
+// turns out to be bad, but it and `numbers` are reused from line to line so
+// they cost very little to keep.
+struct Line {
+    text: Vec<u8>,
+    numbers: Vec<i64>,
+    current: Option<i64>,
+    negative: bool,
+    valid: bool,
+}
+
+impl Default for Line {
+    fn default() -> Line {
+        Line {
+            text: vec![],
+            numbers: vec![],
+            current: None,
+            negative: false,
+            valid: true,
+        }
+    }
+}
+
+impl Line {
+    fn digit(&mut self, byte: u8) {
+        let digit = i64::from(byte - b'0');
+        let value = self.current.unwrap_or(0)
+            .checked_mul(10)
+            .and_then(|value| if self.negative { value.checked_sub(digit) } else { value.checked_add(digit) });
+
+        match value {
+            Some(value) => self.current = Some(value),
+            None => self.valid = false,
+        }
+    }
+
+    // A minus sign is only allowed right before a number.
+    fn minus(&mut self) {
+        if self.negative || self.current.is_some() {
+            self.valid = false;
+        }
+        self.negative = true;
+    }
+
+    fn space(&mut self) {
+        if self.negative && self.current.is_none() {
+            self.valid = false;
+        }
+        self.negative = false;
+
+        if let Some(value) = self.current.take() {
+            self.numbers.push(value);
+        }
+    }
+
+    // Adds the row to the columns if the line was good, or to the skipped lines
+    // if it wasn't blank, then resets for the next line.
+    fn finish(&mut self, number: usize, columns: &mut Columns, skipped: &mut Skipped) {
+        self.space();
+
+        let blank = self.valid && self.numbers.is_empty();
+        let added = self.valid && !blank && columns.push_row(&self.numbers);
+        if !blank && !added {
+            skipped.add(number, &String::from_utf8_lossy(&self.text));
+        }
+
+        let mut text = std::mem::take(&mut self.text);
+        let mut numbers = std::mem::take(&mut self.numbers);
+        text.clear();
+        numbers.clear();
+        *self = Line { text, numbers, ..Line::default() };
+    }
+}
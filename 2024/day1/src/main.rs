@@ -1,10 +1,41 @@
+mod large;
+
+use std::io::BufReader;
+
 use regex::Regex;
 
 type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
 
 fn main() -> Result<(), Error> {
-    // Get a path if there is one.
-    let path = std::env::args_os().nth(1).map(std::path::PathBuf::from);
+    let mut path = None;
+    let mut large = false;
+    let mut lenient = false;
+    let mut matrix = false;
+    let mut pairs = vec![];
+
+    // `--large` switches to the byte parser for huge inputs and `--lenient`
+    // skips lines that don't parse instead of failing. `--pair A,B` compares
+    // columns A and B (counting from 1) and can be given more than once, and
+    // `--matrix` compares every column with every other. Anything else is the
+    // path to the input.
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--large") => large = true,
+            Some("--lenient") => lenient = true,
+            Some("--matrix") => matrix = true,
+            Some("--pair") => {
+                let pair = args.next()
+                    .and_then(|value| parse_pair(value.to_str()?))
+                    .ok_or("--pair needs two column numbers like 1,3")?;
+                pairs.push(pair);
+            }
+            Some(option) if option.starts_with("--") => {
+                return Err(format!("unknown option {}", option).into())
+            }
+            _ => path = Some(std::path::PathBuf::from(arg)),
+        }
+    }
 
     // If we have a path we attempt to open the file or exit. Otherwise we assume
     // stdin
@@ -13,61 +44,223 @@ fn main() -> Result<(), Error> {
         None => Box::new(std::io::stdin()),
     };
 
-    let mut buf = String::new();
-    rdr.read_to_string(&mut buf).unwrap();
-    let input: Vec<&str> = buf.split('\n').filter(|val| !val.is_empty()).collect();
+    let (mut columns, skipped) = if large {
+        large::read_columns(BufReader::with_capacity(1 << 20, rdr))?
+    } else {
+        let mut buf = String::new();
+        rdr.read_to_string(&mut buf).unwrap();
+        parse_columns(&buf)
+    };
+
+    if skipped.count > 0 {
+        let problem = match columns.lists.len() {
+            0 => "lines are not rows of numbers".to_string(),
+            width => format!("lines are not {} numbers", width),
+        };
 
-    part1(input.clone());
-    part2(input);
+        if !lenient {
+            eprint!("{}", skipped);
+            return Err(format!("{} {}, use --lenient to skip them", skipped.count, problem).into())
+        }
+
+        eprintln!("Skipped {} {}:", skipped.count, problem);
+        eprint!("{}", skipped);
+    }
+
+    // Both parts want the lists sorted.
+    for list in columns.lists.iter_mut() {
+        list.sort_unstable();
+    }
+
+    let width = columns.lists.len();
+
+    if width < 2 {
+        return Err(format!("need at least two columns, found {}", width).into())
+    }
+
+    for &(a, b) in &pairs {
+        if a == 0 || b == 0 || a > width || b > width {
+            return Err(format!("--pair {},{} is out of range, the input has {} columns", a, b, width).into())
+        }
+    }
+
+    // With no pairs picked we compare the first two columns like the puzzle
+    // does, unless there are more columns than that to look at.
+    if pairs.is_empty() && !matrix {
+        if width > 2 {
+            matrix = true;
+        } else {
+            pairs.push((1, 2));
+        }
+    }
+
+    for &(a, b) in &pairs {
+        let (list1, list2) = (&columns.lists[a - 1], &columns.lists[b - 1]);
+
+        if (a, b) == (1, 2) && width == 2 {
+            println!("part 1: {}", total_distance(list1, list2));
+            println!("part 2: {}", similarity(list1, list2));
+        } else {
+            println!("part 1 (columns {},{}): {}", a, b, total_distance(list1, list2));
+            println!("part 2 (columns {},{}): {}", a, b, similarity(list1, list2));
+        }
+    }
+
+    if matrix {
+        print_matrix("Distances", &columns.lists, total_distance);
+        print_matrix("Similarity scores (row value counted in column)", &columns.lists, similarity);
+    }
 
     Ok(())
 }
 
-fn parse_lists(input: Vec<&str>) -> (Vec<i32>, Vec<i32>) {
-    let mut list1 = vec![];
-    let mut list2 = vec![];
-    let re = Regex::new(r"^(\d+)\s+(\d+)$").unwrap();
+fn parse_pair(value: &str) -> Option<(usize, usize)> {
+    let (a, b) = value.split_once(',')?;
+    Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+}
+
+// The lists from each column of the input.
+#[derive(Debug, Default)]
+pub struct Columns {
+    lists: Vec<Vec<i64>>,
+}
+
+impl Columns {
+    // The first row decides how many columns there are. Returns false and
+    // leaves the columns alone for a row that doesn't have that many.
+    fn push_row(&mut self, row: &[i64]) -> bool {
+        if self.lists.is_empty() {
+            self.lists = vec![vec![]; row.len()];
+        }
+
+        if row.len() != self.lists.len() {
+            return false
+        }
+
+        for (list, &value) in self.lists.iter_mut().zip(row) {
+            list.push(value);
+        }
+
+        true
+    }
+}
+
+// How many of the skipped lines we hold on to for error messages.
+const SAMPLE_SIZE: usize = 10;
+
+// Lines that weren't blank but didn't parse either. We count all of them but
+// only keep the first few.
+#[derive(Debug, Default)]
+pub struct Skipped {
+    count: usize,
+    sample: Vec<(usize, String)>,
+}
+
+impl Skipped {
+    fn add(&mut self, line: usize, text: &str) {
+        self.count += 1;
 
-    for line in input {
-        if let Some(captures) = re.captures(line) {
-            list1.push(captures[1].parse::<i32>().unwrap());
-            list2.push(captures[2].parse::<i32>().unwrap());
+        if self.sample.len() < SAMPLE_SIZE {
+            self.sample.push((line, text.to_string()));
         }
     }
+}
 
-    (list1, list2)
+impl std::fmt::Display for Skipped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (line, text) in &self.sample {
+            writeln!(f, "    line {}: {:?}", line, text)?;
+        }
+
+        if self.count > self.sample.len() {
+            writeln!(f, "    and {} more", self.count - self.sample.len())?;
+        }
+
+        Ok(())
+    }
 }
 
-fn part1(input: Vec<&str>) {
-    let (mut list1, mut list2) = parse_lists(input);
+fn parse_columns(input: &str) -> (Columns, Skipped) {
+    let mut columns = Columns::default();
+    let mut skipped = Skipped::default();
+    let re = Regex::new(r"^-?\d+(\s+-?\d+)*$").unwrap();
 
-    list1.sort();
-    list2.sort();
+    for (n, line) in input.lines().enumerate() {
+        // Trimming also takes care of a `\r` left over from CRLF endings.
+        let trimmed = line.trim();
 
+        if trimmed.is_empty() {
+            continue
+        }
 
-    let total_distance: i32 = std::iter::zip(list1, list2)
-        .map(|(a, b)| { (a - b).abs() })
-        .sum();
-        // .collect();
+        let row = match re.is_match(trimmed) {
+            true => trimmed.split_whitespace()
+                .map(|value| value.parse::<i64>())
+                .collect::<Result<Vec<i64>, _>>()
+                .ok(),
+            false => None,
+        };
 
-    println!("part 1: {}", total_distance);
+        match row {
+            Some(row) if columns.push_row(&row) => (),
+            _ => skipped.add(n + 1, line),
+        }
+    }
+
+    (columns, skipped)
 }
 
-fn part2(input: Vec<&str>) {
-    let (list1, list2) = parse_lists(input);
+fn total_distance(list1: &[i64], list2: &[i64]) -> i64 {
+    std::iter::zip(list1, list2)
+        .map(|(a, b)| { (a - b).abs() })
+        .sum()
+}
 
-    let mut score = 0;
+// Both lists are sorted, so equal values sit in runs. Walking the two lists
+// together we can count each run once instead of scanning the whole of list 2
+// for every value in list 1.
+fn similarity(list1: &[i64], list2: &[i64]) -> i64 {
+    let mut score: i64 = 0;
+    let (mut i, mut j) = (0, 0);
 
-    for a in list1 {
-        let mut count = 0;
+    while i < list1.len() && j < list2.len() {
+        let a = list1[i];
 
-        for b in &list2 {
-            if a == *b {
-                count+=1;
-            }
+        if a < list2[j] {
+            i += 1;
+            continue
+        }
+
+        if a > list2[j] {
+            j += 1;
+            continue
         }
-        score += a * count
+
+        let run1 = list1[i..].iter().take_while(|&&x| x == a).count();
+        let run2 = list2[j..].iter().take_while(|&&x| x == a).count();
+
+        score += a * run1 as i64 * run2 as i64;
+        i += run1;
+        j += run2;
     }
 
-    println!("part 2: {}", score);
+    score
+}
+
+fn print_matrix(title: &str, lists: &[Vec<i64>], compare: fn(&[i64], &[i64]) -> i64) {
+    let rows: Vec<Vec<String>> = lists.iter()
+        .map(|a| lists.iter().map(|b| compare(a, b).to_string()).collect())
+        .collect();
+
+    let width = rows.iter().flatten().map(|cell| cell.len()).max().unwrap_or(0);
+
+    println!("{}:", title);
+
+    let header: Vec<String> = (1..=lists.len()).map(|column| format!("{:>width$}", column)).collect();
+    println!("    {}", header.join(" "));
+
+    for (row, cells) in rows.iter().enumerate() {
+        let cells: Vec<String> = cells.iter().map(|cell| format!("{:>width$}", cell)).collect();
+        println!("{:>3} {}", row + 1, cells.join(" "));
+    }
 }
@@ -0,0 +1,98 @@
+use std::fmt;
+
+use crate::{levels_to_remove, rule::{Direction, SafetyRule}};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reason {
+    StepTooSmall(i32),
+    StepTooLarge(i32),
+    DirectionChange,
+    // The rule only allows one direction and the levels go the other way.
+    WrongDirection(Direction),
+    SpanTooLarge(i32),
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::StepTooSmall(step) => write!(f, "step of {} is too small", step),
+            Reason::StepTooLarge(step) => write!(f, "step of {} is too large", step),
+            Reason::DirectionChange => write!(f, "levels change direction"),
+            Reason::WrongDirection(Direction::Decreasing) => write!(f, "levels go up but the rule only allows decreasing"),
+            Reason::WrongDirection(_) => write!(f, "levels go down but the rule only allows increasing"),
+            Reason::SpanTooLarge(span) => write!(f, "levels span {} from the first one", span),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Diagnosis {
+    // Fewer than two levels means there are no steps to check. These count as
+    // safe but we call them out since they're probably a bad line.
+    TooShort,
+    Safe,
+    // `index` is the first level of the first pair that breaks the rule. `fix`
+    // is a level we can remove to make the report safe, if there is one.
+    Unsafe {
+        index: usize,
+        reason: Reason,
+        fix: Option<usize>,
+    },
+}
+
+pub fn diagnose(levels: &[i32], rule: &SafetyRule) -> Diagnosis {
+    if levels.len() < 2 {
+        return Diagnosis::TooShort
+    }
+
+    let failure = first_failure(levels, rule);
+
+    match failure {
+        None => Diagnosis::Safe,
+        Some((index, reason)) => Diagnosis::Unsafe {
+            index,
+            reason,
+            fix: levels_to_remove(levels, 1, rule).and_then(|removed| removed.first().copied()),
+        },
+    }
+}
+
+fn first_failure(levels: &[i32], rule: &SafetyRule) -> Option<(usize, Reason)> {
+    // When either direction is fine the first step that goes anywhere picks
+    // it for the rest of the report.
+    let mut increasing = match rule.direction {
+        Direction::Increasing => Some(true),
+        Direction::Decreasing => Some(false),
+        Direction::Either => None,
+    };
+
+    for (i, pair) in levels.windows(2).enumerate() {
+        let (a, b) = (pair[0], pair[1]);
+        let step = (b - a).abs();
+
+        if b != a {
+            match increasing {
+                Some(increasing) if increasing != (a < b) => {
+                    let reason = match rule.direction {
+                        Direction::Either => Reason::DirectionChange,
+                        direction => Reason::WrongDirection(direction),
+                    };
+                    return Some((i, reason))
+                }
+                Some(_) => (),
+                None => increasing = Some(a < b),
+            }
+        }
+
+        if !rule.step_ok(a, b, increasing.unwrap_or(true)) {
+            let reason = if step > rule.max_step { Reason::StepTooLarge(step) } else { Reason::StepTooSmall(step) };
+            return Some((i, reason))
+        }
+
+        if !rule.span_ok(levels[0], b) {
+            return Some((i, Reason::SpanTooLarge((b - levels[0]).abs())))
+        }
+    }
+
+    None
+}
@@ -1,8 +1,50 @@
+mod diagnose;
+mod rule;
+
+use diagnose::Diagnosis;
+use rule::SafetyRule;
+
 type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
 
 fn main() -> Result<(), Error> {
-    // Get a path if there is one.
-    let path = std::env::args_os().nth(1).map(std::path::PathBuf::from);
+    let mut path = None;
+    let mut tolerance = None;
+    let mut diagnose = false;
+    let mut rule = SafetyRule::default();
+
+    // `--tolerance K` also counts the reports that are safe after removing at
+    // most K levels and `--diagnose` explains every unsafe report. The rest of
+    // the options change the safety rule and are applied in order, so they
+    // override anything from an earlier `--config`. Anything that isn't an
+    // option is the path to the input.
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |option: &str| {
+            args.next()
+                .and_then(|value| value.into_string().ok())
+                .ok_or_else(|| format!("{} needs a value", option))
+        };
+
+        match arg.to_str() {
+            Some("--tolerance") => {
+                let k = value("--tolerance")?.parse()
+                    .map_err(|_| "--tolerance needs a number")?;
+                tolerance = Some(k);
+            }
+            Some("--diagnose") => diagnose = true,
+            Some("--config") => rule.load(std::path::Path::new(&value("--config")?))?,
+            Some("--min-step") => rule.set("min_step", &value("--min-step")?)?,
+            Some("--max-step") => rule.set("max_step", &value("--max-step")?)?,
+            Some("--direction") => rule.set("direction", &value("--direction")?)?,
+            Some("--max-span") => rule.set("max_span", &value("--max-span")?)?,
+            Some("--strict") => rule.set("monotonic", "strict")?,
+            Some("--non-strict") => rule.set("monotonic", "non-strict")?,
+            Some(option) if option.starts_with("--") => {
+                return Err(format!("unknown option {}", option).into())
+            }
+            _ => path = Some(std::path::PathBuf::from(arg)),
+        }
+    }
 
     // If we have a path we attempt to open the file or exit. Otherwise we assume
     // stdin
@@ -13,76 +55,191 @@ fn main() -> Result<(), Error> {
 
     let mut buf = String::new();
     rdr.read_to_string(&mut buf).unwrap();
-    let input: Vec<&str> = buf.split('\n').filter(|val| !val.is_empty()).collect();
+    let reports = parse_input(&buf)?;
 
-    part1(input.clone());
-    part2(input);
+    if diagnose {
+        print_diagnoses(&reports, &rule);
+        return Ok(())
+    }
+
+    part1(&reports, &rule);
+    part2(&reports, &rule);
+
+    if let Some(k) = tolerance {
+        let result = count_safe(&reports, k, &rule);
+        println!("Safe after removing at most {} levels: {}", k, result);
+    }
 
     Ok(())
 }
 
-fn parse_input(input: Vec<&str>) -> Vec<Vec<i32>> {
+// Blank lines are skipped, so they don't turn into empty reports.
+fn parse_input(input: &str) -> Result<Vec<Vec<i32>>, Error> {
     let mut reports: Vec<Vec<i32>> = vec![];
 
-    for report in input {
-        let levels = report.split(' ')
-            .map(|level| level.parse::<i32>().unwrap())
-            .collect();
+    for (n, report) in input.lines().enumerate() {
+        if report.trim().is_empty() {
+            continue
+        }
+
+        let levels = report.split_whitespace()
+            .map(|level| level.parse::<i32>()
+                .map_err(|_| format!("line {}: {:?} is not a level", n + 1, level)))
+            .collect::<Result<Vec<i32>, String>>()?;
 
         reports.push(levels)
     }
 
-    reports
+    Ok(reports)
 }
 
-fn check_safety(report: Vec<i32>) -> bool {
-    let levels = report;
+// Finds the fewest levels we can remove, up to `k`, to make the report safe.
+// Returns the indexes to remove or None if it takes more than `k`.
+//
+// Removing levels leaves a subsequence, so we're looking for a safe subsequence
+// that skips at most `k` levels. A safe subsequence can only skip `k` levels
+// between any two levels it keeps, so the predecessor of each kept level is
+// within `k + 1` places of it. That makes this linear in the length of the
+// report for a fixed `k`.
+fn levels_to_remove(levels: &[i32], k: usize, rule: &SafetyRule) -> Option<Vec<usize>> {
+    let n = levels.len();
+
+    if n == 0 {
+        return Some(vec![])
+    }
 
-    let a = levels[0];
-    let b = levels[1];
+    // There's never anything to gain from removing more than every level.
+    let k = k.min(n);
 
-    let increasing = a < b;
+    // Only the first `k + 1` levels can be the first one we keep. Without a
+    // span to check they can all go in one pass, but the span depends on
+    // which level we keep first, so then each one gets a pass of its own.
+    let first = n.min(k + 1);
+    let passes: Vec<std::ops::Range<usize>> = match rule.max_span {
+        None => std::iter::once(0..first).collect(),
+        Some(_) => (0..first).map(|start| start..start + 1).collect(),
+    };
 
-    for i in 0..(levels.len() - 1) {
-        let a = levels[i];
-        let b = levels[i+1];
-        let diff = (a - b).abs();
-        // println!("\tA - B -> {} - {}", a, b);
+    let mut links = vec![None; n * (k + 1)];
 
-        if (diff < 1 || 3 < diff) || ((a < b) != increasing) {
-            // println!("unsafe level {:?}", levels);
-            return false
+    rule.directions().iter()
+        .flat_map(|&increasing| passes.iter().map(move |starts| (increasing, starts.clone())))
+        .filter_map(|(increasing, starts)| best_subsequence(levels, k, rule, increasing, starts, &mut links))
+        .min_by_key(|removed| removed.len())
+}
+
+#[derive(Clone, Copy)]
+enum Link {
+    // Nothing kept before this level.
+    Start,
+    // The previous kept level.
+    From(usize),
+}
+
+// The safe subsequence with the fewest removals that keeps one of `starts` as
+// its first level, as the indexes to remove. The span is measured from the
+// first of `starts`, so with a span to check there should only be one.
+//
+// `links` is scratch space for `levels.len() * (k + 1)` entries, passed in so
+// every pass can share it.
+fn best_subsequence(levels: &[i32], k: usize, rule: &SafetyRule, increasing: bool, starts: std::ops::Range<usize>, links: &mut [Option<Link>]) -> Option<Vec<usize>> {
+    let n = levels.len();
+    let first = starts.start;
+
+    // links[i * (k + 1) + r] is how we got to keeping level i having removed
+    // r levels so far, if we can.
+    let at = |i: usize, r: usize| i * (k + 1) + r;
+    links.fill(None);
+
+    for start in starts {
+        links[at(start, start)] = Some(Link::Start);
+    }
+
+    for i in first + 1..n {
+        if !rule.span_ok(levels[first], levels[i]) {
+            continue
+        }
+
+        for j in i.saturating_sub(k + 1).max(first)..i {
+            if !rule.step_ok(levels[j], levels[i], increasing) {
+                continue
+            }
+
+            let skipped = i - j - 1;
+            for r in skipped..=k {
+                if links[at(i, r)].is_none() && links[at(j, r - skipped)].is_some() {
+                    links[at(i, r)] = Some(Link::From(j));
+                }
+            }
+        }
+    }
+
+    // Whatever comes after the last level we keep gets removed as well.
+    let (mut i, mut r) = (0..n)
+        .flat_map(|i| (0..=k).map(move |r| (i, r)))
+        .filter(|&(i, r)| links[at(i, r)].is_some() && r + (n - 1 - i) <= k)
+        .min_by_key(|&(i, r)| r + (n - 1 - i))?;
+
+    let mut keep = vec![false; n];
+    loop {
+        keep[i] = true;
+
+        match links[at(i, r)].unwrap() {
+            Link::Start => break,
+            Link::From(j) => {
+                r -= i - j - 1;
+                i = j;
+            }
         }
     }
 
-    true
+    Some((0..n).filter(|&i| !keep[i]).collect())
 }
 
-fn part1(input: Vec<&str>) {
-    let reports = parse_input(input);
+fn count_safe(reports: &[Vec<i32>], k: usize, rule: &SafetyRule) -> usize {
+    reports.iter()
+        .filter(|report| levels_to_remove(report, k, rule).is_some())
+        .count()
+}
 
-    let result = reports.iter()
-        .map(|report| { check_safety(report.to_vec()) })
-        .filter(|x| *x)
-        .count();
+fn part1(reports: &[Vec<i32>], rule: &SafetyRule) {
+    let result = count_safe(reports, 0, rule);
 
     println!("Part 1: {}", result);
 }
 
-fn part2(input: Vec<&str>) {
-    let reports = parse_input(input);
-
-    let result = reports.iter()
-        .map(|report| {
-            (0..report.len()).map(|i| {
-                let mut new_report = report.clone();
-                new_report.remove(i);
-                check_safety(new_report)
-            })
-            .any(|result| result)
-        })
-        .filter(|result| *result)
-        .count();
+fn part2(reports: &[Vec<i32>], rule: &SafetyRule) {
+    let result = count_safe(reports, 1, rule);
 
     println!("Part 2: {}", result);
 }
+
+fn print_diagnoses(reports: &[Vec<i32>], rule: &SafetyRule) {
+    let mut unsafe_reports = 0;
+
+    for (n, levels) in reports.iter().enumerate() {
+        let line = levels.iter().map(|level| level.to_string()).collect::<Vec<String>>().join(" ");
+
+        match diagnose::diagnose(levels, rule) {
+            Diagnosis::Safe => (),
+            Diagnosis::TooShort => {
+                println!("Report {}: {}", n + 1, line);
+                println!("    fewer than two levels, nothing to check");
+            }
+            Diagnosis::Unsafe { index, reason, fix } => {
+                unsafe_reports += 1;
+
+                println!("Report {}: {}", n + 1, line);
+                println!("    index {} -> {} ({} -> {}): {}",
+                    index, index + 1, levels[index], levels[index + 1], reason);
+
+                match fix {
+                    Some(i) => println!("    removing index {} ({}) makes it safe", i, levels[i]),
+                    None => println!("    no single removal makes it safe"),
+                }
+            }
+        }
+    }
+
+    println!("{} of {} reports are unsafe", unsafe_reports, reports.len());
+}
@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use crate::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Increasing,
+    Decreasing,
+    Either,
+}
+
+// What makes a report safe. Every setting can come from the command line or
+// from a config file with one `key = value` per line, using these keys:
+//
+//     min_step = 1
+//     max_step = 3
+//     monotonic = strict        # or non-strict, which allows repeated levels
+//                               # whatever min_step is
+//     direction = either        # or increasing, decreasing
+//     max_span = 10             # or none
+//
+// Blank lines and anything after a `#` are ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SafetyRule {
+    pub min_step: i32,
+    pub max_step: i32,
+    pub strict: bool,
+    pub direction: Direction,
+    // Largest difference allowed between the first and last level.
+    pub max_span: Option<i32>,
+}
+
+impl Default for SafetyRule {
+    // The rule from the puzzle.
+    fn default() -> SafetyRule {
+        SafetyRule {
+            min_step: 1,
+            max_step: 3,
+            strict: true,
+            direction: Direction::Either,
+            max_span: None,
+        }
+    }
+}
+
+impl SafetyRule {
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let number = |value: &str| value.parse::<i32>()
+            .map_err(|_| format!("{} needs a number, got {:?}", key, value));
+
+        match key {
+            "min_step" => self.min_step = number(value)?,
+            "max_step" => self.max_step = number(value)?,
+            "monotonic" => self.strict = match value {
+                "strict" => true,
+                "non-strict" => false,
+                _ => return Err(format!("monotonic should be strict or non-strict, got {:?}", value).into()),
+            },
+            "direction" => self.direction = match value {
+                "increasing" => Direction::Increasing,
+                "decreasing" => Direction::Decreasing,
+                "either" => Direction::Either,
+                _ => return Err(format!("direction should be increasing, decreasing or either, got {:?}", value).into()),
+            },
+            "max_span" => self.max_span = match value {
+                "none" => None,
+                _ => Some(number(value)?),
+            },
+            _ => return Err(format!("unknown setting {:?}", key).into()),
+        }
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, path: &Path) -> Result<(), Error> {
+        let config = std::fs::read_to_string(path)?;
+
+        for (n, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+
+            if line.is_empty() {
+                continue
+            }
+
+            let (key, value) = line.split_once('=')
+                .ok_or_else(|| format!("{}:{}: expected `key = value`", path.display(), n + 1))?;
+
+            self.set(key.trim(), value.trim())
+                .map_err(|err| format!("{}:{}: {}", path.display(), n + 1, err))?;
+        }
+
+        Ok(())
+    }
+
+    // The directions to try, as "is it increasing".
+    pub fn directions(&self) -> &'static [bool] {
+        match self.direction {
+            Direction::Increasing => &[true],
+            Direction::Decreasing => &[false],
+            Direction::Either => &[true, false],
+        }
+    }
+
+    // A repeated level is only a problem for a strict rule. `min_step` is for
+    // steps that actually go somewhere.
+    pub fn step_ok(&self, a: i32, b: i32, increasing: bool) -> bool {
+        let diff = if increasing { b - a } else { a - b };
+
+        if diff == 0 {
+            return !self.strict
+        }
+
+        (self.min_step.max(1)..=self.max_step).contains(&diff)
+    }
+
+    // Levels only ever move one way, so the span is just the distance between
+    // the first and last level.
+    pub fn span_ok(&self, first: i32, last: i32) -> bool {
+        match self.max_span {
+            Some(span) => (last - first).abs() <= span,
+            None => true,
+        }
+    }
+}
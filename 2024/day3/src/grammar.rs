@@ -0,0 +1,51 @@
+use nom::{
+    bytes::complete::{tag, take_while_m_n},
+    character::complete::one_of,
+    combinator::{cond, map_res, opt, recognize},
+    sequence::{delimited, pair, separated_pair},
+    IResult,
+};
+
+// What counts as an operand in `mul(x,y)`. Both parts share one of these so
+// they can't disagree about which instructions are real.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grammar {
+    pub min_digits: usize,
+    pub max_digits: usize,
+    pub allow_sign: bool,
+}
+
+impl Grammar {
+    // The rules from the puzzle: one to three digits and no sign.
+    pub const STRICT: Grammar = Grammar {
+        min_digits: 1,
+        max_digits: 3,
+        allow_sign: false,
+    };
+
+    // Anything that fits in an i32, sign included. This is what nom's `i32`
+    // parser accepts.
+    pub const LENIENT: Grammar = Grammar {
+        min_digits: 1,
+        max_digits: usize::MAX,
+        allow_sign: true,
+    };
+
+    pub fn operand<'a>(&self, input: &'a str) -> IResult<&'a str, i32> {
+        map_res(
+            recognize(pair(
+                cond(self.allow_sign, opt(one_of("+-"))),
+                take_while_m_n(self.min_digits, self.max_digits, |c: char| c.is_ascii_digit()),
+            )),
+            str::parse,
+        )(input)
+    }
+
+    pub fn pair<'a>(&self, input: &'a str) -> IResult<&'a str, (i32, i32)> {
+        separated_pair(|i| self.operand(i), tag(","), |i| self.operand(i))(input)
+    }
+
+    pub fn mul<'a>(&self, input: &'a str) -> IResult<&'a str, (i32, i32)> {
+        delimited(tag("mul("), |i| self.pair(i), tag(")"))(input)
+    }
+}
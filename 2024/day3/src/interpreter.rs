@@ -0,0 +1,211 @@
+use aho_corasick::{AhoCorasick, MatchKind};
+use nom::{bytes::complete::tag, combinator::value, sequence::terminated, IResult};
+
+use crate::{grammar::Grammar, scanner::Scanner, Instruction};
+
+// Instructions take at most this many operands. Unused slots are left as 0.
+pub const MAX_ARGS: usize = 2;
+
+pub type Args = [i32; MAX_ARGS];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    // Works on the sum, as long as the machine is enabled.
+    Value,
+    // Changes whether the machine is enabled.
+    Control,
+}
+
+// Everything the interpreter needs to know about one instruction. `prefix` is
+// what the scanner searches for, then `parse` reads the rest of the
+// instruction (operands and the closing paren) and `exec` applies it.
+#[derive(Clone, Copy)]
+pub struct Definition {
+    pub name: &'static str,
+    pub prefix: &'static str,
+    pub kind: Kind,
+    pub parse: for<'a> fn(&Grammar, &'a str) -> IResult<&'a str, Args>,
+    pub exec: fn(&mut Machine, &Args),
+}
+
+#[derive(Clone, Debug)]
+pub struct Machine {
+    pub sum: i64,
+    pub enabled: bool,
+    // Enabled flags saved by `enter()`, restored by `exit()`.
+    pub scopes: Vec<bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub sum: i64,
+    pub enabled: bool,
+    pub depth: usize,
+}
+
+// One line of the execution trace.
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub instruction: Instruction,
+    pub before: Snapshot,
+    pub after: Snapshot,
+}
+
+fn no_args<'a>(_: &Grammar, input: &'a str) -> IResult<&'a str, Args> {
+    value([0; MAX_ARGS], tag(")"))(input)
+}
+
+fn two_args<'a>(grammar: &Grammar, input: &'a str) -> IResult<&'a str, Args> {
+    let (remaining, (x, y)) = terminated(|i| grammar.pair(i), tag(")"))(input)?;
+    Ok((remaining, [x, y]))
+}
+
+pub const MUL: Definition = Definition {
+    name: "mul",
+    prefix: "mul(",
+    kind: Kind::Value,
+    parse: two_args,
+    exec: |machine, &[x, y]| if machine.enabled { machine.sum += i64::from(x) * i64::from(y) },
+};
+
+pub const DO: Definition = Definition {
+    name: "do",
+    prefix: "do(",
+    kind: Kind::Control,
+    parse: no_args,
+    exec: |machine, _| machine.enabled = true,
+};
+
+pub const DONT: Definition = Definition {
+    name: "don't",
+    prefix: "don't(",
+    kind: Kind::Control,
+    parse: no_args,
+    exec: |machine, _| machine.enabled = false,
+};
+
+pub const ADD: Definition = Definition {
+    name: "add",
+    prefix: "add(",
+    kind: Kind::Value,
+    parse: two_args,
+    exec: |machine, &[x, y]| if machine.enabled { machine.sum += i64::from(x) + i64::from(y) },
+};
+
+pub const NEG: Definition = Definition {
+    name: "neg",
+    prefix: "neg(",
+    kind: Kind::Value,
+    parse: no_args,
+    exec: |machine, _| if machine.enabled { machine.sum = -machine.sum },
+};
+
+pub const ENTER: Definition = Definition {
+    name: "enter",
+    prefix: "enter(",
+    kind: Kind::Control,
+    parse: no_args,
+    exec: |machine, _| machine.scopes.push(machine.enabled),
+};
+
+// An `exit()` without a matching `enter()` does nothing.
+pub const EXIT: Definition = Definition {
+    name: "exit",
+    prefix: "exit(",
+    kind: Kind::Control,
+    parse: no_args,
+    exec: |machine, _| {
+        if let Some(enabled) = machine.scopes.pop() {
+            machine.enabled = enabled;
+        }
+    },
+};
+
+// The set of instructions the interpreter understands, along with a searcher
+// for their prefixes.
+pub struct Registry {
+    definitions: Vec<Definition>,
+    automaton: AhoCorasick,
+}
+
+impl Registry {
+    pub fn new(definitions: Vec<Definition>) -> Registry {
+        // Prefer the longest prefix when one is a prefix of another, so adding
+        // something like `do_twice(` can't be shadowed by `do(`.
+        let automaton = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(definitions.iter().map(|definition| definition.prefix))
+            .expect("prefixes are valid");
+
+        Registry { definitions, automaton }
+    }
+
+    pub fn part2() -> Registry {
+        Registry::new(vec![MUL, DO, DONT])
+    }
+
+    pub fn extended() -> Registry {
+        Registry::new(vec![MUL, DO, DONT, ADD, NEG, ENTER, EXIT])
+    }
+
+    // The same registry with every control instruction taken out. Part 1 is
+    // part 2 without the ability to disable anything.
+    pub fn without_control(&self) -> Registry {
+        let definitions = self.definitions.iter()
+            .filter(|definition| definition.kind != Kind::Control)
+            .copied()
+            .collect();
+
+        Registry::new(definitions)
+    }
+
+    pub fn automaton(&self) -> &AhoCorasick {
+        &self.automaton
+    }
+
+    pub fn get(&self, id: usize) -> &Definition {
+        &self.definitions[id]
+    }
+}
+
+impl Machine {
+    pub fn new() -> Machine {
+        Machine {
+            sum: 0,
+            enabled: true,
+            scopes: vec![],
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            sum: self.sum,
+            enabled: self.enabled,
+            depth: self.scopes.len(),
+        }
+    }
+
+    pub fn execute(&mut self, registry: &Registry, instruction: &Instruction) {
+        (registry.get(instruction.definition).exec)(self, &instruction.args);
+    }
+
+    // Runs every instruction in the input, recording the machine state around
+    // each one.
+    pub fn trace<'a>(&'a mut self, registry: &'a Registry, grammar: Grammar, input: &'a str) -> impl Iterator<Item = Step> + 'a {
+        Scanner::new(input, registry, grammar).map(move |instruction| {
+            let before = self.snapshot();
+            self.execute(registry, &instruction);
+            let after = self.snapshot();
+
+            Step { instruction, before, after }
+        })
+    }
+
+    pub fn run(&mut self, registry: &Registry, grammar: Grammar, input: &str) -> i64 {
+        for instruction in Scanner::new(input, registry, grammar) {
+            self.execute(registry, &instruction);
+        }
+
+        self.sum
+    }
+}
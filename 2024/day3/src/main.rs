@@ -1,25 +1,53 @@
-use regex::Regex;
-use nom::{
-    branch::alt,
-    combinator::value,
-    character::complete::i32,
-    character::complete::anychar,
-    character::complete::alphanumeric0,
-    character::complete::alphanumeric1,
-    bytes::complete::{tag, take_while_m_n},
-    combinator::map_res,
-    sequence::Tuple,
-    IResult,
-    Parser,
-};
-use nom::bytes::complete::take_while;
-use nom::sequence::{separated_pair, delimited};
+mod grammar;
+mod interpreter;
+mod scanner;
+
+use std::ops::Range;
+
+use grammar::Grammar;
+use interpreter::{Args, Kind, Machine, Registry, Snapshot};
 
 type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
 
+enum Mode {
+    Solve,
+    Report,
+    Highlight,
+    Trace,
+}
+
 fn main() -> Result<(), Error> {
-    // Get a path if there is one.
-    let path = std::env::args_os().nth(1).map(std::path::PathBuf::from);
+    let mut mode = Mode::Solve;
+    let mut grammar = Grammar::STRICT;
+    let mut registry = Registry::part2();
+    let mut path = None;
+
+    // Options are applied in order, so `--lenient --max-digits 5` starts from
+    // the lenient grammar and then caps the digits. Anything that isn't an
+    // option is the path to the input.
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--report") => mode = Mode::Report,
+            Some("--highlight") => mode = Mode::Highlight,
+            Some("--trace") => mode = Mode::Trace,
+            Some("--extended") => registry = Registry::extended(),
+            Some("--strict") => grammar = Grammar::STRICT,
+            Some("--lenient") => grammar = Grammar::LENIENT,
+            Some("--signed") => grammar.allow_sign = true,
+            Some("--unsigned") => grammar.allow_sign = false,
+            Some("--min-digits") => grammar.min_digits = number_arg("--min-digits", args.next())?,
+            Some("--max-digits") => grammar.max_digits = number_arg("--max-digits", args.next())?,
+            Some(option) if option.starts_with("--") => {
+                return Err(format!("unknown option {}", option).into())
+            }
+            _ => path = Some(std::path::PathBuf::from(arg)),
+        }
+    }
+
+    if grammar.min_digits > grammar.max_digits {
+        return Err(format!("--min-digits {} is more than --max-digits {}", grammar.min_digits, grammar.max_digits).into())
+    }
 
     // If we have a path we attempt to open the file or exit. Otherwise we assume
     // stdin
@@ -31,113 +59,161 @@ fn main() -> Result<(), Error> {
     let mut buf = String::new();
     rdr.read_to_string(&mut buf).unwrap();
     let input = &buf;
-    // let input: Vec<&str> = buf.split('\n').filter(|val| !val.is_empty()).collect();
 
-    part1(input);
-    part2(input);
+    match mode {
+        Mode::Solve => {
+            part1(input, &registry, grammar);
+            part2(input, &registry, grammar);
+        }
+        Mode::Report => report(input, grammar),
+        Mode::Highlight => highlight(input, &registry, grammar),
+        Mode::Trace => trace(input, &registry, grammar),
+    }
 
     Ok(())
 }
 
-#[derive(Clone, Copy, Debug)]
-enum Op {
-    Mul(i32, i32),
-    Do,
-    Dont,
-    Skip,
+fn number_arg(option: &str, value: Option<std::ffi::OsString>) -> Result<usize, Error> {
+    value.as_ref()
+        .and_then(|value| value.to_str())
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("{} needs a number", option).into())
 }
 
-impl Op {
-    fn keep(self) -> bool {
-        match self {
-            Op::Skip => false,
-            _ => true,
-        }
-    }
+// An instruction along with the bytes of the input it came from.
+// `definition` is its index in the registry that found it.
+#[derive(Clone, Debug)]
+pub struct Instruction {
+    definition: usize,
+    args: Args,
+    span: Range<usize>,
 }
 
-fn part1(input: &str) {
-    let re = Regex::new(r"mul\((\d{0,3}),(\d{0,3})\)").unwrap();
-    let result = re.captures_iter(input)
-        .map(|caps| {
-            let (_, [x, y]) = caps.extract();
-            (x.parse::<i32>().unwrap(), y.parse::<i32>().unwrap())
-        })
-        .map(|(x, y)| { x * y })
-        .sum::<i32>();
+fn part1(input: &str, registry: &Registry, grammar: Grammar) {
+    let result = Machine::new().run(&registry.without_control(), grammar, input);
 
     println!("Part 1: {}", result);
 }
 
-fn parse(input: &str) -> Vec<Op> {
-    let (_, parsed) = nom::multi::many0(parse_op)(input).unwrap();
-    // let ops = parsed.iter()
-    //     .filter(|&op| {
-    //         match op {
-    //             Op::Skip => false,
-    //             _ => true
-    //         }
-    //     })
-    //     .collect();
-
-    parsed.iter().filter_map(|&op| {
-        match op {
-            Op::Skip => None,
-            _ => Some(op),
-        }
-    })
-    .collect()
+fn part2(input: &str, registry: &Registry, grammar: Grammar) {
+    let result = Machine::new().run(registry, grammar, input);
+
+    println!("Part 2: {}", result);
 }
 
-fn parse_op(input: &str) -> IResult<&str, Op> {
-    alt((
-        parse_mul,
-        value(Op::Do, tag("do()")),
-        value(Op::Dont, tag("don't()")),
-        value(Op::Skip, anychar),
-    ))(input)
+// Lists every `mul` that the lenient grammar and the one we were given don't
+// agree on, either because only one of them accepts it or because they read
+// different operands out of it.
+fn report(input: &str, grammar: Grammar) {
+    let mut differences = 0;
+
+    for (start, _) in input.match_indices("mul(") {
+        let candidate = &input[start..];
+        let chosen = grammar.mul(candidate).ok();
+        let lenient = Grammar::LENIENT.mul(candidate).ok();
+
+        let same = match (chosen, lenient) {
+            (Some((_, a)), Some((_, b))) => a == b,
+            (None, None) => true,
+            _ => false,
+        };
+
+        if same {
+            continue
+        }
+
+        differences += 1;
+
+        // Show as much of the input as either grammar consumed.
+        let end = [chosen, lenient].iter()
+            .flatten()
+            .map(|(remaining, _)| input.len() - remaining.len())
+            .max()
+            .unwrap_or(start);
+
+        let describe = |parsed: Option<(&str, (i32, i32))>| match parsed {
+            Some((_, (x, y))) => format!("{} * {}", x, y),
+            _ => "rejected".to_string(),
+        };
+
+        println!("offset {}: {}  chosen: {}  lenient: {}",
+            start, &input[start..end], describe(chosen), describe(lenient));
+    }
+
+    println!("{} instructions differ", differences);
 }
 
-fn parse_integer_pair(input: &str) -> IResult<&str, (i32, i32)> {
-    separated_pair(
-        i32,
-        tag(","),
-        i32
-    )(input)
+const RESET: &str = "\x1b[0m";
+const ENABLED: &str = "\x1b[1;32m";
+const DISABLED: &str = "\x1b[2;31m";
+const TOGGLE: &str = "\x1b[1;36m";
+
+// Prints the memory with every instruction coloured by what part 2 does with
+// it, followed by where each instruction is and what it adds to the sum.
+fn highlight(input: &str, registry: &Registry, grammar: Grammar) {
+    let mut machine = Machine::new();
+    let mut last = 0;
+    let mut rows = vec![];
+
+    for step in machine.trace(registry, grammar, input) {
+        let span = step.instruction.span;
+        let text = &input[span.clone()];
+
+        let (colour, contribution) = match registry.get(step.instruction.definition).kind {
+            Kind::Control => (TOGGLE, state_change(&step.before, &step.after)),
+            Kind::Value if step.before.enabled => (ENABLED, format!("{:+}", step.after.sum - step.before.sum)),
+            Kind::Value => (DISABLED, "skipped".to_string()),
+        };
+
+        print!("{}{}{}{}", &input[last..span.start], colour, text, RESET);
+        last = span.end;
+
+        rows.push(format!("{:>8}..{:<8} {:<16} {}", span.start, span.end, text, contribution));
+    }
+
+    let rest = &input[last..];
+    print!("{}", rest);
+    if !rest.ends_with('\n') {
+        println!();
+    }
+
+    for row in rows {
+        println!("{}", row);
+    }
+
+    println!("Part 2: {}", machine.sum);
 }
 
-fn parse_mul(input: &str) -> IResult<&str, Op> {
-   let (remaining, (x, y)) = delimited(
-        tag("mul("),
-        parse_integer_pair,
-        tag(")")
-    )(input)?;
+// What a control instruction did, like `enabled true->false, depth 1->0`.
+fn state_change(before: &Snapshot, after: &Snapshot) -> String {
+    let mut changes = vec![];
+
+    if before.enabled != after.enabled {
+        changes.push(format!("enabled {}->{}", before.enabled, after.enabled));
+    }
+    if before.depth != after.depth {
+        changes.push(format!("depth {}->{}", before.depth, after.depth));
+    }
 
-    Ok((remaining, Op::Mul(x, y)))
+    match changes.is_empty() {
+        true => "no change".to_string(),
+        false => changes.join(", "),
+    }
 }
 
-fn part2(input: &str) {
-    let ops = parse(input);
-    let mut enabled = true;
+// Prints the machine state after every instruction.
+fn trace(input: &str, registry: &Registry, grammar: Grammar) {
+    let mut machine = Machine::new();
 
-    let mut result = 0;
+    for step in machine.trace(registry, grammar, input) {
+        let span = step.instruction.span;
 
-    for op in ops {
-        match op {
-            Op::Do => {
-                enabled = true
-            },
-            Op::Dont => {
-                enabled = false
-            }
-            Op::Mul(x, y) => {
-                if enabled {
-                    result += x * y
-                }
-            }
-            _ => unreachable!("We've already removed skip opts")
-        }
+        println!("{:>8}..{:<8} {:<6} {:<16} sum={} enabled={} depth={}",
+            span.start, span.end,
+            registry.get(step.instruction.definition).name,
+            &input[span.clone()],
+            step.after.sum, step.after.enabled, step.after.depth);
     }
 
-    println!("Part 2: {}", result);
+    println!("Result: {}", machine.sum);
 }
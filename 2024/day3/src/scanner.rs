@@ -0,0 +1,46 @@
+use aho_corasick::Input;
+
+use crate::{grammar::Grammar, interpreter::Registry, Instruction};
+
+// Walks the corrupted memory looking for anything that could start one of the
+// registry's instructions, skipping straight over the junk in between. Only
+// complete instructions are yielded and nothing is allocated per instruction,
+// so this is fine to run over dumps that are megabytes long.
+pub struct Scanner<'a> {
+    registry: &'a Registry,
+    grammar: Grammar,
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(input: &'a str, registry: &'a Registry, grammar: Grammar) -> Scanner<'a> {
+        Scanner {
+            registry,
+            grammar,
+            input,
+            pos: 0,
+        }
+    }
+}
+
+impl Iterator for Scanner<'_> {
+    type Item = Instruction;
+
+    fn next(&mut self) -> Option<Instruction> {
+        loop {
+            let candidate = self.registry.automaton().find(Input::new(self.input).range(self.pos..))?;
+            self.pos = candidate.end();
+
+            let definition = candidate.pattern().as_usize();
+
+            // A prefix that isn't followed by the rest of a valid instruction
+            // is just more junk, so keep looking after it.
+            if let Ok((remaining, args)) = (self.registry.get(definition).parse)(&self.grammar, &self.input[self.pos..]) {
+                self.pos = self.input.len() - remaining.len();
+
+                return Some(Instruction { definition, args, span: candidate.start()..self.pos })
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::{rules::RuleMatrix, topo_sort, OrderError, Rule, Update};
+
+// What we can say about a rule set as a whole, rather than one update at a
+// time.
+pub struct Analysis {
+    pub pages: usize,
+    pub rules: usize,
+    // Strongly connected components with more than one page, or a page with a
+    // rule pointing at itself. Any of these means the rules contradict each
+    // other.
+    pub cycles: Vec<Vec<u32>>,
+    // Rules that already follow from a chain of other rules. Only rules between
+    // different strongly connected components count, since inside a cycle
+    // every rule follows from going the rest of the way round.
+    pub redundant: Vec<Rule>,
+    // Pages that show up in updates but never in a rule.
+    pub unconstrained: Vec<u32>,
+    // A single order for every page, if the rules pin one down.
+    pub order: Result<Update, OrderError>,
+}
+
+type Graph = BTreeMap<u32, Vec<u32>>;
+
+pub fn analyze(rules: &[Rule], updates: &[Update]) -> Analysis {
+    let mut graph: Graph = BTreeMap::new();
+    let mut unique_rules = BTreeSet::new();
+
+    for &(a, b) in rules {
+        if unique_rules.insert((a, b)) {
+            graph.entry(a).or_default().push(b);
+            graph.entry(b).or_default();
+        }
+    }
+
+    let constrained: BTreeSet<u32> = graph.keys().copied().collect();
+    let unconstrained: BTreeSet<u32> = updates.iter()
+        .flatten()
+        .copied()
+        .filter(|page| !constrained.contains(page))
+        .collect();
+
+    let all_pages: Update = constrained.iter().chain(unconstrained.iter()).copied().collect();
+    let components = components(&graph);
+
+    let cycles: Vec<Vec<u32>> = components.iter()
+        .filter(|component| component.len() > 1 || graph[&component[0]].contains(&component[0]))
+        .cloned()
+        .collect();
+
+    // The sort stops at the first page it can't decide on, which could be
+    // ambiguity before it ever gets to a cycle. A cycle is the bigger problem
+    // so report that first.
+    let order = match cycles.first() {
+        Some(cycle) => Err(OrderError::Cycle { update: all_pages.clone(), cycle: cycle.clone() }),
+        None => topo_sort(&all_pages, &RuleMatrix::from_rules(rules)),
+    };
+
+    Analysis {
+        pages: all_pages.len(),
+        rules: unique_rules.len(),
+        cycles,
+        redundant: redundant_rules(&graph, &components),
+        unconstrained: unconstrained.into_iter().collect(),
+        order,
+    }
+}
+
+// Tarjan's strongly connected components algorithm.
+struct Tarjan<'a> {
+    graph: &'a Graph,
+    next_index: usize,
+    index: HashMap<u32, usize>,
+    low_link: HashMap<u32, usize>,
+    stack: Vec<u32>,
+    on_stack: HashSet<u32>,
+    components: Vec<Vec<u32>>,
+}
+
+impl Tarjan<'_> {
+    fn visit(&mut self, page: u32) {
+        self.index.insert(page, self.next_index);
+        self.low_link.insert(page, self.next_index);
+        self.next_index += 1;
+        self.stack.push(page);
+        self.on_stack.insert(page);
+
+        for &next in &self.graph[&page] {
+            if !self.index.contains_key(&next) {
+                self.visit(next);
+                let low = self.low_link[&page].min(self.low_link[&next]);
+                self.low_link.insert(page, low);
+            } else if self.on_stack.contains(&next) {
+                let low = self.low_link[&page].min(self.index[&next]);
+                self.low_link.insert(page, low);
+            }
+        }
+
+        // If we're the root of a component then everything above us on the
+        // stack belongs to it.
+        if self.low_link[&page] == self.index[&page] {
+            let mut component = vec![];
+
+            loop {
+                let member = self.stack.pop().unwrap();
+                self.on_stack.remove(&member);
+                component.push(member);
+
+                if member == page {
+                    break
+                }
+            }
+
+            component.sort();
+            self.components.push(component);
+        }
+    }
+}
+
+fn components(graph: &Graph) -> Vec<Vec<u32>> {
+    let mut tarjan = Tarjan {
+        graph,
+        next_index: 0,
+        index: HashMap::new(),
+        low_link: HashMap::new(),
+        stack: vec![],
+        on_stack: HashSet::new(),
+        components: vec![],
+    };
+
+    for &page in graph.keys() {
+        if !tarjan.index.contains_key(&page) {
+            tarjan.visit(page);
+        }
+    }
+
+    tarjan.components
+}
+
+// A rule `a|b` is redundant if we can still get from `a` to `b` after taking
+// that rule away. Rules inside a component are skipped, which leaves the
+// redundant edges of the condensation.
+fn redundant_rules(graph: &Graph, components: &[Vec<u32>]) -> Vec<Rule> {
+    let component_of: HashMap<u32, usize> = components.iter()
+        .enumerate()
+        .flat_map(|(i, component)| component.iter().map(move |&page| (page, i)))
+        .collect();
+
+    let mut redundant = vec![];
+
+    for (&a, successors) in graph {
+        for &b in successors {
+            if component_of[&a] == component_of[&b] {
+                continue
+            }
+
+            // Coming back round to `a` would let us take the rule we're
+            // trying to do without, so it counts as already seen.
+            let mut stack: Vec<u32> = successors.iter().copied().filter(|&page| page != b).collect();
+            let mut seen = HashSet::from([a]);
+
+            while let Some(page) = stack.pop() {
+                if page == b {
+                    redundant.push((a, b));
+                    break
+                }
+
+                if seen.insert(page) {
+                    stack.extend(&graph[&page]);
+                }
+            }
+        }
+    }
+
+    redundant
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rule_out_of_a_cycle_is_not_redundant() {
+        let analysis = analyze(&[(1, 2), (2, 1), (1, 3)], &[]);
+
+        assert_eq!(analysis.redundant, vec![]);
+    }
+}
@@ -0,0 +1,109 @@
+use std::io::{BufRead, Lines};
+
+use nom::{
+    bytes::complete::tag, character::complete::{digit1, space0}, combinator::{all_consuming, map_res}, multi::separated_list1, sequence::{delimited, separated_pair}, IResult
+};
+use thiserror::Error;
+
+use crate::{Rule, Update};
+
+// The input is a section of rules, one or more blank lines, and then a section
+// of updates. Each line is parsed on its own so line endings, a missing final
+// newline and stray blank lines at the end don't matter, and spaces are allowed
+// around the separators. Line numbers in errors are 1-based.
+#[derive(Error, Debug)]
+pub enum ParseError {
+    #[error("line {line} in the rules section: expected a rule like `47|53`, found {text:?}")]
+    Rule {
+        line: usize,
+        text: String,
+    },
+    #[error("line {line} in the updates section: expected an update like `75,47,61`, found {text:?}")]
+    Update {
+        line: usize,
+        text: String,
+    },
+    #[error("input ended before the blank line that starts the updates section")]
+    MissingUpdates,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+fn page(input: &str) -> IResult<&str, u32> {
+    map_res(digit1, str::parse)(input)
+}
+
+fn separator<'a>(sep: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    delimited(space0, tag(sep), space0)
+}
+
+fn parse_rule(input: &str) -> IResult<&str, Rule> {
+    all_consuming(separated_pair(page, separator("|"), page))(input)
+}
+
+fn parse_update(input: &str) -> IResult<&str, Update> {
+    all_consuming(separated_list1(separator(","), page))(input)
+}
+
+// Reads the whole rules section and hands back the updates as an iterator, so
+// only one update needs to be in memory at a time.
+pub fn parse<R: BufRead>(reader: R) -> Result<(Vec<Rule>, Updates<R>), ParseError> {
+    let mut lines = reader.lines();
+    let mut line = 0;
+    let mut rules = vec![];
+
+    loop {
+        let text = match lines.next() {
+            Some(text) => text?,
+            None => return Err(ParseError::MissingUpdates),
+        };
+        line += 1;
+
+        let trimmed = text.trim();
+
+        if trimmed.is_empty() {
+            // Blank lines before the first rule aren't the section break.
+            if rules.is_empty() {
+                continue
+            }
+            break
+        }
+
+        match parse_rule(trimmed) {
+            Ok((_, rule)) => rules.push(rule),
+            Err(_) => return Err(ParseError::Rule { line, text }),
+        }
+    }
+
+    Ok((rules, Updates { lines, line }))
+}
+
+pub struct Updates<R> {
+    lines: Lines<R>,
+    line: usize,
+}
+
+impl<R: BufRead> Iterator for Updates<R> {
+    type Item = Result<Update, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let text = match self.lines.next()? {
+                Ok(text) => text,
+                Err(err) => return Some(Err(err.into())),
+            };
+            self.line += 1;
+
+            let trimmed = text.trim();
+
+            if trimmed.is_empty() {
+                continue
+            }
+
+            return match parse_update(trimmed) {
+                Ok((_, update)) => Some(Ok(update)),
+                Err(_) => Some(Err(ParseError::Update { line: self.line, text })),
+            }
+        }
+    }
+}
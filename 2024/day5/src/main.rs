@@ -1,47 +1,67 @@
-use std::{cmp::Ordering, collections::HashMap};
+mod analysis;
+mod input;
+mod repair;
+mod rules;
 
-use nom::{
-    bytes::complete::tag, character::complete::{digit1, newline}, combinator::map_res, multi::{many1, many_till, separated_list1}, sequence::{terminated, tuple}, IResult
-};
+use std::{collections::HashMap, fmt, hint::black_box, io::BufReader, time::Instant};
+
+use thiserror::Error;
+
+use input::ParseError;
+use rules::RuleMatrix;
 
 type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
 
 type Rule = (u32, u32);
 type Update = Vec<u32>;
+// The original lookup table, only kept around to benchmark against.
 type RulesTable = HashMap<u32, Vec<u32>>;
 
-fn parse_rule(input: &str) -> IResult<&str, Rule> {
-    let (remaining, (a, _, b)) = terminated(
-            tuple((
-            map_res(digit1, str::parse),
-            tag("|"),
-            map_res(digit1, str::parse),
-        )),
-        newline
-        )(input)?;
-    Ok((remaining, (a, b)))
+// A rule `page_a|page_b` that an update breaks by putting `page_b` first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Violation {
+    page_a: u32,
+    index_a: usize,
+    page_b: u32,
+    index_b: usize,
 }
 
-fn parse_update(input: &str) -> IResult<&str, Update> {
-    terminated(separated_list1(tag(","), map_res(digit1, str::parse)), newline)(input)
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "page {} at index {} must precede page {} at index {}, rule {}|{}",
+            self.page_a, self.index_a, self.page_b, self.index_b, self.page_a, self.page_b
+        )
+    }
 }
 
-fn parse(input: &str) -> IResult<&str, (RulesTable, Vec<Update>)> {
-    let (remaining, (rules, _)) = many_till(parse_rule, newline)(input)?;
-    let (remaining, updates) = many1(parse_update)(remaining)?;
+#[derive(Error, Debug)]
+pub enum OrderError {
+    #[error("rules for update {update:?} form a cycle: {cycle:?}")]
+    Cycle {
+        update: Update,
+        cycle: Vec<u32>,
+    },
+    #[error("rules for update {update:?} don't decide which of {pages:?} comes first")]
+    Ambiguous {
+        update: Update,
+        pages: Vec<u32>,
+    },
+}
 
-    // Build rules table for easy lookup
+fn build_table(rules: &[Rule]) -> RulesTable {
     let mut rule_table = HashMap::new();
 
-    for (a, b) in rules {
+    for &(a, b) in rules {
         let rules = rule_table.entry(a).or_insert(vec![]);
         rules.push(b);
     }
 
-    Ok((remaining, (rule_table, updates)))
+    rule_table
 }
 
-fn is_valid_update(update: &Update, rule_table: &RulesTable) -> bool {
+fn is_valid_update_table(update: &Update, rule_table: &RulesTable) -> bool {
     // To check that our rules are being respected we're going to loop over
     // each page number and then loop over any page numbers "forward" in the
     // list. For each future page we check to see if there is a rule that states
@@ -67,77 +87,365 @@ fn is_valid_update(update: &Update, rule_table: &RulesTable) -> bool {
     !is_invalid
 }
 
-// Returns true if page 1 must come before page 2
-fn page_must_be_before(page1: &u32, page2: &u32, table: &RulesTable) -> bool {
-    match table.get(page1) {
-        Some(rules) => rules.contains(page2),
-        None => false,
+// An update is valid when no page has a rule saying it should come before one
+// of the pages in front of it.
+fn is_valid_update(update: &Update, rules: &RuleMatrix) -> bool {
+    let is_invalid = update.iter()
+        .enumerate()
+        .any(|(i, page)| {
+            update[i + 1..].iter().any(|f| rules.must_precede(*f, *page))
+        });
+
+    !is_invalid
+}
+
+// Lists every rule the update breaks. This is the same check as
+// `is_valid_update` except we keep going after the first problem.
+fn violations(update: &Update, rules: &RuleMatrix) -> Vec<Violation> {
+    let mut found = vec![];
+
+    for (index_b, page_b) in update.iter().enumerate() {
+        for (index_a, page_a) in update.iter().enumerate().skip(index_b + 1) {
+            if rules.must_precede(*page_a, *page_b) {
+                found.push(Violation {
+                    page_a: *page_a,
+                    index_a,
+                    page_b: *page_b,
+                    index_b,
+                });
+            }
+        }
+    }
+
+    found
+}
+
+// Orders the pages of an update using Kahn's algorithm over the rules that
+// only involve pages in this update. The puzzle promises a single correct order
+// so if we ever have more than one page to pick from we report it rather than
+// guessing.
+fn topo_sort(update: &Update, rules: &RuleMatrix) -> Result<Update, OrderError> {
+    let n = update.len();
+    let mut successors = vec![vec![]; n];
+    let mut predecessors = vec![vec![]; n];
+    let mut in_degree = vec![0; n];
+
+    for i in 0..n {
+        for j in 0..n {
+            if i != j && rules.must_precede(update[i], update[j]) {
+                successors[i].push(j);
+                predecessors[j].push(i);
+                in_degree[j] += 1;
+            }
+        }
+    }
+
+    let mut ready: Vec<usize> = (0..n).filter(|&i| in_degree[i] == 0).collect();
+    let mut sorted = Vec::with_capacity(n);
+
+    while let Some(i) = ready.pop() {
+        if !ready.is_empty() {
+            let mut pages: Vec<u32> = ready.iter().map(|&j| update[j]).collect();
+            pages.push(update[i]);
+            pages.sort();
+
+            return Err(OrderError::Ambiguous { update: update.clone(), pages })
+        }
+
+        sorted.push(update[i]);
+
+        for &j in &successors[i] {
+            in_degree[j] -= 1;
+            if in_degree[j] == 0 {
+                ready.push(j);
+            }
+        }
+    }
+
+    if sorted.len() < n {
+        // Every page we couldn't place still has a predecessor we couldn't
+        // place either, so walking backwards from any of them has to run into
+        // a page we've already seen.
+        let mut path = vec![];
+        let mut current = (0..n).find(|&i| in_degree[i] > 0).unwrap();
+
+        while !path.contains(&current) {
+            path.push(current);
+            current = *predecessors[current].iter()
+                .find(|&&j| in_degree[j] > 0)
+                .unwrap();
+        }
+
+        let start = path.iter().position(|&i| i == current).unwrap();
+        let cycle = path[start..].iter().rev().map(|&i| update[i]).collect();
+
+        return Err(OrderError::Cycle { update: update.clone(), cycle })
+    }
+
+    Ok(sorted)
+}
+
+// Works out both parts in a single pass so updates can be streamed in.
+fn solve<I>(rules: &RuleMatrix, updates: I) -> Result<(), Error>
+where
+    I: Iterator<Item = Result<Update, ParseError>>,
+{
+    let mut part1 = 0;
+    let mut part2 = 0;
+    let mut moves = 0;
+
+    for update in updates {
+        let update = update?;
+
+        if is_valid_update(&update, rules) {
+            part1 += update[update.len()/2];
+        } else {
+            let sorted = topo_sort(&update, rules)?;
+            part2 += sorted[sorted.len()/2];
+            moves += repair::repair(&update, rules)?.moves.len();
+        }
+    }
+
+    println!("Part 1: {}", part1);
+    println!("Part 2: {}", part2);
+    println!("Repair moves: {}", moves);
+
+    Ok(())
+}
+
+// Prints every invalid update along with the rules it breaks.
+fn explain<I>(rules: &RuleMatrix, updates: I) -> Result<(), Error>
+where
+    I: Iterator<Item = Result<Update, ParseError>>,
+{
+    let mut invalid = 0;
+    let mut count = 0;
+
+    for (n, update) in updates.enumerate() {
+        let update = update?;
+        let found = violations(&update, rules);
+        count += 1;
+
+        if found.is_empty() {
+            continue
+        }
+
+        invalid += 1;
+
+        println!("Update {}: {}", n + 1, join_pages(&update));
+
+        for violation in found {
+            println!("    {}", violation);
+        }
     }
+
+    println!("{} of {} updates are invalid", invalid, count);
+
+    Ok(())
 }
 
-fn page_order(page1: &u32, page2: &u32, table: &RulesTable) -> Ordering {
-    // If page 1 must be before page 2 we return Less
-    // And if page 2 must be before page 1 we return Greater
-    // otherwise we return equal
-    if page_must_be_before(page1, page2, table) { return Ordering::Less }
-    if page_must_be_before(page2, page1, table) { return Ordering::Greater }
+// Times the validity check with the original hashmap table against the bit
+// matrix over the same updates.
+fn bench(rules: &[Rule], updates: &[Update]) -> Result<(), Error> {
+    const ROUNDS: u32 = 1000;
+
+    let now = Instant::now();
+    let table = build_table(rules);
+    let build = now.elapsed();
+
+    let now = Instant::now();
+    let mut table_valid = 0;
+    for _ in 0..ROUNDS {
+        table_valid = updates.iter()
+            .filter(|update| is_valid_update_table(black_box(update), &table))
+            .count();
+    }
+    let check = now.elapsed();
+
+    println!("HashMap table: built in {:?}, {} rounds in {:?} ({:?} per round)",
+        build, ROUNDS, check, check / ROUNDS);
+
+    let now = Instant::now();
+    let matrix = RuleMatrix::from_rules(rules);
+    let build = now.elapsed();
+
+    let now = Instant::now();
+    let mut matrix_valid = 0;
+    for _ in 0..ROUNDS {
+        matrix_valid = updates.iter()
+            .filter(|update| is_valid_update(black_box(update), &matrix))
+            .count();
+    }
+    let check = now.elapsed();
+
+    println!("Bit matrix:    built in {:?}, {} rounds in {:?} ({:?} per round)",
+        build, ROUNDS, check, check / ROUNDS);
 
-    Ordering::Equal
+    if table_valid != matrix_valid {
+        return Err(format!("tables disagree: {} vs {} valid updates", table_valid, matrix_valid).into())
+    }
+
+    Ok(())
 }
 
-fn part1(input: &str) -> Result<(), Error> {
-    let (_, (rules, updates)) = parse(input).unwrap();
+// Prints the fewest moves that fix each invalid update.
+fn repair<I>(rules: &RuleMatrix, updates: I) -> Result<(), Error>
+where
+    I: Iterator<Item = Result<Update, ParseError>>,
+{
+    let mut total = 0;
+
+    for (n, update) in updates.enumerate() {
+        let update = update?;
 
-    let good_updates: Vec<Update> = updates.into_iter()
-        .filter(|update| is_valid_update(update, &rules))
-        .collect();
+        if is_valid_update(&update, rules) {
+            continue
+        }
 
-    let result = good_updates.iter()
-        .map(|update| update[update.len()/2])
-        .sum::<u32>();
+        let repair = repair::repair(&update, rules)?;
+        total += repair.moves.len();
+
+        println!("Update {}: {} -> {} ({} moves)",
+            n + 1, join_pages(&update), join_pages(&repair.result), repair.moves.len());
+
+        for step in &repair.moves {
+            println!("    {}", step);
+        }
+    }
+
+    println!("{} moves in total", total);
 
-    println!("Part 1: {}", result);
     Ok(())
 }
 
-fn part2(input: &str) -> Result<(), Error> {
-    let (_, (rules, updates)) = parse(input).unwrap();
+fn join_pages(pages: &[u32]) -> String {
+    pages.iter().map(|page| page.to_string()).collect::<Vec<String>>().join(",")
+}
+
+// Looks at the rule set as a whole and reports anything odd about it.
+fn analyze(rules: &[Rule], updates: &[Update]) -> Result<(), Error> {
+    let analysis = analysis::analyze(rules, updates);
+
+    println!("{} rules over {} pages", analysis.rules, analysis.pages);
+
+    println!("Cycles: {}", analysis.cycles.len());
+    for component in &analysis.cycles {
+        println!("    {} pages: {}", component.len(), join_pages(component));
+    }
 
-    let bad_updates:Vec<Update> = updates.into_iter()
-        .filter(|update| !is_valid_update(update, &rules))
-        .collect::<Vec<Update>>();
+    println!("Redundant rules: {} (rules within a cycle are left out)", analysis.redundant.len());
+    for (a, b) in &analysis.redundant {
+        println!("    {}|{}", a, b);
+    }
 
-    let result = bad_updates.iter()
-        .map(|update| {
-            let mut sorted = update.clone();
-            sorted.sort_by(|a, b| page_order(a, b, &rules));
-            sorted
-        })
-        .map(|update| update[update.len()/2])
-        .sum::<u32>();
+    println!("Unconstrained pages: {}", analysis.unconstrained.len());
+    if !analysis.unconstrained.is_empty() {
+        println!("    {}", join_pages(&analysis.unconstrained));
+    }
 
-    println!("Part 2: {}", result);
+    match analysis.order {
+        Ok(order) => println!("Total order: {}", join_pages(&order)),
+        Err(OrderError::Cycle { .. }) => println!("Total order: none, the rules form a cycle"),
+        Err(OrderError::Ambiguous { pages, .. }) => {
+            println!("Total order: none, nothing decides which of {} comes first", join_pages(&pages))
+        }
+    }
 
     Ok(())
 }
 
-fn main() -> Result<(), Error> {
-    // Get a path if there is one.
-    let path = std::env::args_os().nth(1).map(std::path::PathBuf::from);
+// Lists the rules that follow from chains of other rules. This is only for
+// looking at. The puzzle only applies rules between pages in the same update,
+// so solving with the closure would change the answers.
+fn closure(rules: &RuleMatrix) {
+    let closure = rules.transitive_closure();
+
+    // A chain that comes back to where it started puts the page before itself.
+    // That says the page is in a cycle rather than anything about the order,
+    // so those are listed on their own.
+    let mut implied: Vec<Rule> = vec![];
+    let mut in_cycle: Vec<u32> = vec![];
+
+    for (a, b) in closure.rules() {
+        if a == b {
+            in_cycle.push(a);
+        } else if !rules.must_precede(a, b) {
+            implied.push((a, b));
+        }
+    }
+    implied.sort();
+    in_cycle.sort();
+
+    let given = rules.rules().filter(|(a, b)| a != b).count();
+    println!("{} rules given, {} once chains of rules are followed", given, given + implied.len());
+
+    println!("Implied rules: {}", implied.len());
+    for (a, b) in &implied {
+        println!("    {}|{}", a, b);
+    }
+
+    println!("Pages that come before themselves through a cycle: {}", in_cycle.len());
+    if !in_cycle.is_empty() {
+        println!("    {}", join_pages(&in_cycle));
+    }
+}
+
+enum Mode {
+    Solve,
+    Explain,
+    Bench,
+    Analyze,
+    Repair,
+    Closure,
+}
+
+// Errors are printed with Display so the parse and ordering errors read as
+// sentences rather than structs.
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut mode = Mode::Solve;
+    let mut path = None;
+
+    // Options can come in any order, anything else is the path to the input.
+    for arg in std::env::args_os().skip(1) {
+        match arg.to_str() {
+            Some("--explain") => mode = Mode::Explain,
+            Some("--bench") => mode = Mode::Bench,
+            Some("--analyze") => mode = Mode::Analyze,
+            Some("--repair") => mode = Mode::Repair,
+            Some("--closure") => mode = Mode::Closure,
+            Some(option) if option.starts_with("--") => {
+                return Err(format!("unknown option {}", option).into())
+            }
+            _ => path = Some(std::path::PathBuf::from(arg)),
+        }
+    }
 
     // If we have a path we attempt to open the file or exit. Otherwise we assume
     // stdin
-    let mut rdr: Box<dyn std::io::Read> = match path {
+    let rdr: Box<dyn std::io::Read> = match path {
         Some(path) => Box::new(std::fs::File::open(path).expect("could not open file")),
         None => Box::new(std::io::stdin()),
     };
 
-    let mut buf = String::new();
-    rdr.read_to_string(&mut buf).unwrap();
-    let input = &buf;
+    let (rule_list, updates) = input::parse(BufReader::new(rdr))?;
 
-    let _ = part1(input);
-    let _ = part2(input);
+    let rules = RuleMatrix::from_rules(&rule_list);
+
+    match mode {
+        Mode::Solve => solve(&rules, updates)?,
+        Mode::Explain => explain(&rules, updates)?,
+        Mode::Bench => bench(&rule_list, &updates.collect::<Result<Vec<Update>, ParseError>>()?)?,
+        Mode::Analyze => analyze(&rule_list, &updates.collect::<Result<Vec<Update>, ParseError>>()?)?,
+        Mode::Repair => repair(&rules, updates)?,
+        Mode::Closure => closure(&rules),
+    }
 
     Ok(())
 }
@@ -0,0 +1,192 @@
+use std::fmt;
+
+use crate::{rules::RuleMatrix, OrderError, Update};
+
+// Take `page` out of the update and put it back in at `to`. Both indexes are
+// for the update as it is just before the move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub page: u32,
+    pub from: usize,
+    pub to: usize,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "move page {} from index {} to index {}", self.page, self.from, self.to)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Repair {
+    pub moves: Vec<Move>,
+    pub result: Update,
+}
+
+// Finds the fewest single-page moves that make an update follow the rules.
+//
+// Page `i` is "inverted" with a later page `j` if the rules say, directly or
+// through other pages in the update, that `j` has to come first. Any set of
+// pages with no inversions between them can stay where they are, so we want
+// the biggest such set and move everything else. Inversions form a partial
+// order, so that set is its largest antichain, which Dilworth and König let us
+// read off a maximum bipartite matching.
+pub fn repair(update: &Update, rules: &RuleMatrix) -> Result<Repair, OrderError> {
+    let n = update.len();
+
+    // reach[i][j] is true when page i has to come before page j. Only pages in
+    // the update count, rules through other pages don't apply to it.
+    let mut reach = vec![vec![false; n]; n];
+    for i in 0..n {
+        for j in 0..n {
+            reach[i][j] = i != j && rules.must_precede(update[i], update[j]);
+        }
+    }
+    for k in 0..n {
+        let through = reach[k].clone();
+        for row in reach.iter_mut().filter(|row| row[k]) {
+            for (cell, &next) in row.iter_mut().zip(&through) {
+                *cell |= next;
+            }
+        }
+    }
+
+    let cycle: Vec<u32> = (0..n).filter(|&i| reach[i][i]).map(|i| update[i]).collect();
+    if !cycle.is_empty() {
+        return Err(OrderError::Cycle { update: update.clone(), cycle })
+    }
+
+    let inverted = |i: usize, j: usize| i < j && reach[j][i];
+    let keep = largest_antichain(n, inverted);
+    let result = target_order(update, &reach, &keep);
+
+    // Move the other pages one at a time, in the order they show up in the
+    // result. Each one goes right after whichever page it follows in the
+    // result, which is already in place by the time we get to it.
+    let mut current = update.clone();
+    let mut settled = keep;
+    let mut moves = vec![];
+
+    for (position, &page) in result.iter().enumerate() {
+        let i = update.iter().position(|&p| p == page).unwrap();
+        if settled[i] {
+            continue
+        }
+
+        let from = current.iter().position(|&p| p == page).unwrap();
+        current.remove(from);
+
+        let to = match position {
+            0 => 0,
+            _ => current.iter().position(|&p| p == result[position - 1]).unwrap() + 1,
+        };
+        current.insert(to, page);
+
+        settled[i] = true;
+        moves.push(Move { page, from, to });
+    }
+
+    Ok(Repair { moves, result })
+}
+
+// Largest set of indexes with no `before` relation between any two of them.
+// `before` has to be a strict partial order.
+fn largest_antichain<F>(n: usize, before: F) -> Vec<bool>
+where
+    F: Fn(usize, usize) -> bool,
+{
+    // Bipartite graph with a left and right copy of every element and an edge
+    // from i on the left to j on the right when i comes before j.
+    let edges: Vec<Vec<usize>> = (0..n)
+        .map(|i| (0..n).filter(|&j| before(i, j)).collect())
+        .collect();
+
+    // Kuhn's augmenting path matching.
+    fn augment(i: usize, edges: &[Vec<usize>], seen: &mut [bool], matched: &mut [Option<usize>]) -> bool {
+        for &j in &edges[i] {
+            if seen[j] {
+                continue
+            }
+            seen[j] = true;
+
+            if matched[j].is_none() || augment(matched[j].unwrap(), edges, seen, matched) {
+                matched[j] = Some(i);
+                return true
+            }
+        }
+
+        false
+    }
+
+    let mut matched_right: Vec<Option<usize>> = vec![None; n];
+    for i in 0..n {
+        augment(i, &edges, &mut vec![false; n], &mut matched_right);
+    }
+
+    let mut matched_left = vec![None; n];
+    for (j, i) in matched_right.iter().enumerate() {
+        if let Some(i) = i {
+            matched_left[*i] = Some(j);
+        }
+    }
+
+    // König: walk alternating paths from the unmatched left vertices. The
+    // minimum vertex cover is every left vertex we didn't reach plus every
+    // right vertex we did, and the largest antichain is whatever has neither
+    // copy in the cover.
+    let mut left_reached = vec![false; n];
+    let mut right_reached = vec![false; n];
+    let mut stack: Vec<usize> = (0..n).filter(|&i| matched_left[i].is_none()).collect();
+
+    while let Some(i) = stack.pop() {
+        if left_reached[i] {
+            continue
+        }
+        left_reached[i] = true;
+
+        for &j in &edges[i] {
+            if right_reached[j] {
+                continue
+            }
+            right_reached[j] = true;
+
+            if let Some(next) = matched_right[j] {
+                stack.push(next);
+            }
+        }
+    }
+
+    (0..n).map(|i| left_reached[i] && !right_reached[i]).collect()
+}
+
+// A valid order for the update that leaves the kept pages in the order they
+// already have. Ties go to whichever page came first originally so we don't
+// shuffle anything we don't have to.
+fn target_order(update: &Update, reach: &[Vec<bool>], keep: &[bool]) -> Update {
+    let n = update.len();
+    let kept: Vec<usize> = (0..n).filter(|&i| keep[i]).collect();
+
+    let mut before = reach.to_vec();
+    for pair in kept.windows(2) {
+        before[pair[0]][pair[1]] = true;
+    }
+
+    let mut in_degree: Vec<usize> = (0..n).map(|j| (0..n).filter(|&i| before[i][j]).count()).collect();
+    let mut placed = vec![false; n];
+    let mut result = Vec::with_capacity(n);
+
+    while result.len() < n {
+        let i = (0..n).find(|&i| !placed[i] && in_degree[i] == 0).unwrap();
+
+        placed[i] = true;
+        result.push(update[i]);
+
+        for j in 0..n {
+            if before[i][j] {
+                in_degree[j] -= 1;
+            }
+        }
+    }
+
+    result
+}
@@ -0,0 +1,105 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::Rule;
+
+// Pages below this get a bit in the dense matrix. Puzzle inputs only use two
+// digit page numbers so anything above this lands in the fallback set.
+const DENSE_PAGES: usize = 128;
+const WORDS_PER_ROW: usize = DENSE_PAGES / 64;
+
+// Adjacency matrix for the ordering rules. Bit `b` of row `a` is set when there
+// is a rule `a|b`, so checking a pair of pages is a couple of shifts instead of
+// a scan through a list.
+#[derive(Clone, Debug)]
+pub struct RuleMatrix {
+    bits: Vec<u64>,
+    sparse: HashSet<Rule>,
+}
+
+impl RuleMatrix {
+    pub fn new() -> RuleMatrix {
+        RuleMatrix {
+            bits: vec![0; DENSE_PAGES * WORDS_PER_ROW],
+            sparse: HashSet::new(),
+        }
+    }
+
+    pub fn from_rules(rules: &[Rule]) -> RuleMatrix {
+        let mut matrix = RuleMatrix::new();
+
+        for &(a, b) in rules {
+            matrix.insert(a, b);
+        }
+
+        matrix
+    }
+
+    // Word index and bit mask for a pair of pages, if both fit in the matrix.
+    fn position(a: u32, b: u32) -> Option<(usize, u64)> {
+        let (a, b) = (a as usize, b as usize);
+
+        if a < DENSE_PAGES && b < DENSE_PAGES {
+            Some((a * WORDS_PER_ROW + b / 64, 1 << (b % 64)))
+        } else {
+            None
+        }
+    }
+
+    pub fn insert(&mut self, a: u32, b: u32) {
+        match RuleMatrix::position(a, b) {
+            Some((word, mask)) => self.bits[word] |= mask,
+            None => { self.sparse.insert((a, b)); }
+        }
+    }
+
+    // Returns true if there is a rule that page a must come before page b
+    pub fn must_precede(&self, a: u32, b: u32) -> bool {
+        match RuleMatrix::position(a, b) {
+            Some((word, mask)) => self.bits[word] & mask != 0,
+            None => self.sparse.contains(&(a, b)),
+        }
+    }
+
+    // Every rule in the matrix, dense ones first.
+    pub fn rules(&self) -> impl Iterator<Item = Rule> + '_ {
+        let dense = (0..DENSE_PAGES).flat_map(move |a| {
+            (0..DENSE_PAGES)
+                .filter(move |&b| self.must_precede(a as u32, b as u32))
+                .map(move |b| (a as u32, b as u32))
+        });
+
+        dense.chain(self.sparse.iter().copied())
+    }
+
+    // Adds a rule `a|c` whenever there's a chain of rules `a|b`, `b|c`, ...
+    // This only makes sense when the rules don't form a cycle. The real inputs
+    // do, in which case every page in the cycle ends up before every other.
+    pub fn transitive_closure(&self) -> RuleMatrix {
+        let mut successors: HashMap<u32, Vec<u32>> = HashMap::new();
+
+        for (a, b) in self.rules() {
+            successors.entry(a).or_default().push(b);
+        }
+
+        let mut closure = RuleMatrix::new();
+
+        for &start in successors.keys() {
+            let mut stack = successors[&start].clone();
+            let mut seen = HashSet::new();
+
+            while let Some(page) = stack.pop() {
+                if !seen.insert(page) {
+                    continue
+                }
+
+                closure.insert(start, page);
+
+                if let Some(next) = successors.get(&page) {
+                    stack.extend(next);
+                }
+            }
+        }
+
+        closure
+    }
+}
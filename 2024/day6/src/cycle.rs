@@ -0,0 +1,130 @@
+// Cycle detection for deterministic simulations.
+//
+// A simulation is a starting state plus a step function that either advances
+// the state in place (`Sim::Cont`) or reports that there is no next state
+// (`Sim::Fin`). Because the step function is deterministic, the sequence of
+// states either terminates or eventually repeats forever. Both detectors here
+// only ever hold a couple of states at once so they run in constant memory no
+// matter how long the simulation runs before it loops.
+
+pub enum Sim {
+    Cont,
+    Fin,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    // Index of the first state that is part of the cycle.
+    pub start: usize,
+    // Number of states in the cycle.
+    pub period: usize,
+}
+
+// Advances the state `n` times. The caller has to know these steps exist,
+// which is true for any prefix of a sequence we've already walked.
+fn advance<S, F>(state: &mut S, step: &mut F, n: usize)
+where
+    F: FnMut(&mut S) -> Sim,
+{
+    for _ in 0..n {
+        step(state);
+    }
+}
+
+// Finds the start of the cycle once we know its period. One pointer gets a
+// head start of `period` steps and then both move together; they meet at the
+// first state of the cycle.
+fn cycle_start<S, F>(init: &S, step: &mut F, period: usize) -> usize
+where
+    S: Clone + Eq,
+    F: FnMut(&mut S) -> Sim,
+{
+    let mut tortoise = init.clone();
+    let mut hare = init.clone();
+    advance(&mut hare, step, period);
+
+    let mut start = 0;
+    while tortoise != hare {
+        step(&mut tortoise);
+        step(&mut hare);
+        start += 1;
+    }
+
+    start
+}
+
+// Floyd's tortoise and hare. The hare moves two states for every one the
+// tortoise moves. If the hare ever runs off the end there is no cycle,
+// otherwise the two meet somewhere inside the cycle.
+pub fn floyd<S, F>(init: S, mut step: F) -> Option<Cycle>
+where
+    S: Clone + Eq,
+    F: FnMut(&mut S) -> Sim,
+{
+    let mut tortoise = init.clone();
+    let mut hare = init.clone();
+
+    loop {
+        for _ in 0..2 {
+            if let Sim::Fin = step(&mut hare) {
+                return None
+            }
+        }
+
+        // The hare has already been here so this step always exists.
+        step(&mut tortoise);
+
+        if tortoise == hare {
+            break
+        }
+    }
+
+    // The meeting point is inside the cycle so walking around it once gives
+    // us the period.
+    let mut period = 1;
+    let mut hare = tortoise.clone();
+    step(&mut hare);
+    while tortoise != hare {
+        step(&mut hare);
+        period += 1;
+    }
+
+    let start = cycle_start(&init, &mut step, period);
+
+    Some(Cycle { start, period })
+}
+
+// Brent's algorithm. The tortoise teleports to the hare every power of two
+// steps, which finds the period directly and usually needs fewer calls to
+// `step` than Floyd.
+pub fn brent<S, F>(init: S, mut step: F) -> Option<Cycle>
+where
+    S: Clone + Eq,
+    F: FnMut(&mut S) -> Sim,
+{
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = init.clone();
+    let mut hare = init.clone();
+
+    if let Sim::Fin = step(&mut hare) {
+        return None
+    }
+
+    while tortoise != hare {
+        if power == period {
+            tortoise = hare.clone();
+            power *= 2;
+            period = 0;
+        }
+
+        if let Sim::Fin = step(&mut hare) {
+            return None
+        }
+        period += 1;
+    }
+
+    let start = cycle_start(&init, &mut step, period);
+
+    Some(Cycle { start, period })
+}
@@ -1,14 +1,41 @@
+mod cycle;
+
 use std::{
     collections::HashSet, time::Instant}
 ;
 use rayon::prelude::*;
 
-use nom::{branch::alt, bytes::complete::tag, character::complete::newline, combinator::value, multi::many1, sequence::terminated, IResult};
+use nom::{branch::alt, bytes::complete::tag, combinator::value, multi::many0, IResult};
 use thiserror::Error;
 use anyhow::Result;
 
+use cycle::{brent, floyd, Sim};
+
 #[derive(Error, Debug)]
 pub enum PuzzleError {
+    #[error("input is empty")]
+    Empty,
+    #[error("no guard found on the map")]
+    NoGuard,
+    #[error("found a second guard at line {line}, column {column} (the first is at line {first_line}, column {first_column})")]
+    MultipleGuards {
+        first_line: usize,
+        first_column: usize,
+        line: usize,
+        column: usize,
+    },
+    #[error("line {line} has {found} cells but the map is {expected} wide")]
+    RaggedLine {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    #[error("unknown character {found:?} at line {line}, column {column}")]
+    UnknownChar {
+        line: usize,
+        column: usize,
+        found: char,
+    },
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -18,7 +45,6 @@ enum Cell {
     Guard(Dir),
 }
 
-type PuzzleInput = Vec<Vec<Cell>>;
 type Steps = Vec<(Dir, usize, usize)>;
 
 #[derive(Clone, Copy, Debug, PartialEq, Hash, Eq)]
@@ -71,24 +97,79 @@ fn parse_guard(input: &str) -> IResult<&str, Cell> {
     Ok((remaining, Cell::Guard(dir)))
 }
 
-fn parse(input: &str) -> IResult<&str, PuzzleInput> {
-    many1(
-        terminated(
-            many1(
-                alt((
-                    value(Cell::Empty, tag(".")),
-                    value(Cell::Obstacle, tag("#")),
-                    parse_guard
-                ))
-            ),
-            newline
-        )
+fn parse_row(input: &str) -> IResult<&str, Vec<Cell>> {
+    many0(
+        alt((
+            value(Cell::Empty, tag(".")),
+            value(Cell::Obstacle, tag("#")),
+            parse_guard
+        ))
     )(input)
 }
 
-enum Sim {
-    Cont,
-    Fin,
+// Builds the grid and pulls the guard out of it, replacing their cell with an
+// empty one. Line and column numbers in errors are 1-based.
+fn load(input: &str) -> Result<(Grid, Guard), PuzzleError> {
+    let input = input.trim_end_matches(['\n', '\r']);
+
+    if input.is_empty() {
+        return Err(PuzzleError::Empty)
+    }
+
+    let mut cells: Vec<Vec<Cell>> = vec![];
+    let mut guard: Option<Guard> = None;
+
+    for (y, line) in input.lines().enumerate() {
+        // The row parser can't fail since it matches zero or more cells, it
+        // just stops at the first character it doesn't know.
+        let (remaining, mut row) = parse_row(line).expect("row parser is infallible");
+
+        if let Some(found) = remaining.chars().next() {
+            return Err(PuzzleError::UnknownChar {
+                line: y + 1,
+                column: line.len() - remaining.len() + 1,
+                found,
+            })
+        }
+
+        if let Some(first) = cells.first() {
+            if row.len() != first.len() {
+                return Err(PuzzleError::RaggedLine {
+                    line: y + 1,
+                    expected: first.len(),
+                    found: row.len(),
+                })
+            }
+        }
+
+        for (x, cell) in row.iter_mut().enumerate() {
+            if let Cell::Guard(dir) = *cell {
+                if let Some(Guard(_, first_y, first_x)) = guard {
+                    return Err(PuzzleError::MultipleGuards {
+                        first_line: first_y + 1,
+                        first_column: first_x + 1,
+                        line: y + 1,
+                        column: x + 1,
+                    })
+                }
+
+                guard = Some(Guard::new(dir, y, x));
+                *cell = Cell::Empty;
+            }
+        }
+
+        cells.push(row);
+    }
+
+    let guard = guard.ok_or(PuzzleError::NoGuard)?;
+
+    let grid = Grid {
+        height: cells.len(),
+        width: cells[0].len(),
+        cells,
+    };
+
+    Ok((grid, guard))
 }
 
 fn next_step(guard: &mut Guard, grid: &Grid) -> Sim {
@@ -133,29 +214,23 @@ fn simulate(guard: Guard, grid: Grid) -> Steps {
     steps
 }
 
-fn find_loop(guard: Guard, grid: Grid) -> bool {
-    let mut slow_guard = guard;
-    let mut is_loop = false;
-    let mut history = HashSet::new();
-
-    loop {
-        history.insert(slow_guard);
-
-        // We can skip the check for the slow guard since our fast guard will find the exit much faster
-        // let _ = next_step(&mut slow_guard, &grid);
-        if let Sim::Fin = next_step(&mut slow_guard, &grid) {
-            break
-        }
+// Which cycle finder to use. Brent's is the default, Floyd's is there to
+// compare against.
+#[derive(Clone, Copy, Debug)]
+enum Detector {
+    Brent,
+    Floyd,
+}
 
-        // If we've seen this exact same direction at this point we *have* to be
-        // in a loop since the board never changes.
-        if history.contains(&slow_guard) {
-            is_loop = true;
-            break
-        }
-    }
+fn find_loop(guard: Guard, grid: Grid, detector: Detector) -> bool {
+    // The board never changes, so if the guard ever ends up in the exact same
+    // position and direction again they *have* to be in a loop.
+    let cycle = match detector {
+        Detector::Brent => brent(guard, |guard| next_step(guard, &grid)),
+        Detector::Floyd => floyd(guard, |guard| next_step(guard, &grid)),
+    };
 
-    is_loop
+    cycle.is_some()
 }
 
 fn part1(guard: Guard, grid: Grid) -> Result<()> {
@@ -169,7 +244,7 @@ fn part1(guard: Guard, grid: Grid) -> Result<()> {
     Ok(())
 }
 
-fn part2(guard: Guard, grid: Grid) -> Result<()> {
+fn part2(guard: Guard, grid: Grid, detector: Detector) -> Result<()> {
     // Build permutations of the grid by looking at the actual path of the guard and
     // only adding obstacles in that path.
     let mut steps = simulate(guard, grid.clone());
@@ -214,7 +289,7 @@ fn part2(guard: Guard, grid: Grid) -> Result<()> {
     // How is this literally the best way to do pmap?
     let result: u32 = permutations.par_iter()
         .map(|grid| {
-            if find_loop(guard, grid.clone()) { 1 } else { 0 }
+            if find_loop(guard, grid.clone(), detector) { 1 } else { 0 }
         })
         .sum();
 
@@ -226,8 +301,18 @@ fn part2(guard: Guard, grid: Grid) -> Result<()> {
 }
 
 fn main() -> Result<()> {
-    // Get a path if there is one.
-    let path = std::env::args_os().nth(1).map(std::path::PathBuf::from);
+    let mut path = None;
+    let mut detector = Detector::Brent;
+
+    // `--floyd` finds loops with Floyd's algorithm instead of Brent's. Anything
+    // else is the path to the input.
+    for arg in std::env::args_os().skip(1) {
+        match arg.to_str() {
+            Some("--floyd") => detector = Detector::Floyd,
+            Some(option) if option.starts_with("--") => anyhow::bail!("unknown option {}", option),
+            _ => path = Some(std::path::PathBuf::from(arg)),
+        }
+    }
 
     // If we have a path we attempt to open the file or exit. Otherwise we assume
     // stdin
@@ -240,33 +325,10 @@ fn main() -> Result<()> {
     rdr.read_to_string(&mut buf).unwrap();
     let input = &buf;
 
-    let (_, parsed) = parse(input).unwrap();
-
-    // We need to find the position of the guard and replace it with a empty cell
-    let mut guard = None;
-
-    let mut grid = parsed.clone();
-
-    for y in 0..grid.len() {
-        for x in 0..grid[y].len() {
-            if let Cell::Guard(dir) = grid[y][x] {
-                guard = Some(Guard::new(dir, y, x));
-                grid[y][x] = Cell::Empty;
-            }
-        }
-    }
-
-    let guard = guard.unwrap();
-
-    // Mark the variable as immutable again
-    let grid = Grid{
-        height: grid.len(),
-        width: grid[0].len(),
-        cells: grid.clone()
-    };
+    let (grid, guard) = load(input)?;
 
     let _ = part1(guard, grid.clone());
-    let _ = part2(guard, grid);
+    let _ = part2(guard, grid, detector);
 
     Ok(())
 }